@@ -146,7 +146,7 @@ fn encode_neighbor_if_then_constraint(
         let neighbors = [-1i32, 1]
             .iter()
             .map(|i| number + i)
-            .filter(|&i| 0 <= i && i < 5)
+            .filter(|&i| (0..5).contains(&i))
             .map(|neighbor_number| House {
                 number: neighbor_number as u32,
                 property: then_prop,
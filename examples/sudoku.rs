@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use anyhow::Context;
 use itertools::iproduct;
 use satoxid::{
-    constraints::{AtLeastK, ExactlyK},
+    constraints::{AtLeastK, AtMostOne, Or},
     Backend, CadicalEncoder, Encoder, Model,
 };
 use structopt::StructOpt;
@@ -19,8 +19,8 @@ fn encode_sudoku_rules(encoder: &mut Encoder<Tile, impl Backend>) {
     // Each Tile must have exactly one value
     for (x, y) in iproduct!(0..9, 0..9) {
         let lits = (1..=9).map(|value| Tile { x, y, value });
-        let constraint = ExactlyK { k: 1, lits };
-        encoder.add_constraint(constraint);
+        encoder.add_constraint(Or(lits.clone()));
+        encoder.add_constraint(AtMostOne { lits });
     }
 
     // Every value appears in every row.
@@ -0,0 +1,341 @@
+use std::iter;
+
+use crate::{
+    guard::GuardedBackend, Backend, Encoder, IncrementalSolver, Projection, SatVar,
+    SolveResult,
+};
+
+const DEFAULT_SEED: u64 = 0x5a70_c1d5;
+
+/// SplitMix64 pseudo random number generator.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn coin(&mut self) -> bool {
+        self.next() & 1 == 1
+    }
+}
+
+/// Random XOR constraint over the projected variables.
+struct Xor {
+    /// Activation literal of the encoded constraint.
+    guard: i32,
+    /// Indices of the selected variables in the projection.
+    vars: Vec<usize>,
+    odd: bool,
+}
+
+/// State shared by all cells of one approximate count.
+///
+/// Every model is blocked only once under `guard`, the models found so far are
+/// kept and counted again for each cell they belong to.
+struct Cells {
+    projection: Vec<i32>,
+    guard: i32,
+    xors: Vec<Xor>,
+    models: Vec<Vec<bool>>,
+}
+
+impl Cells {
+    /// Returns true if `model` satisfies the first `m` XOR constraints.
+    fn contains(&self, model: &[bool], m: usize) -> bool {
+        self.xors[..m].iter().all(|xor| {
+            let ones = xor.vars.iter().filter(|&&i| model[i]).count();
+            (ones % 2 == 1) == xor.odd
+        })
+    }
+}
+
+/// Computes `value << shift`, saturating at `u128::MAX`.
+fn saturating_shl(value: u128, shift: usize) -> u128 {
+    if value == 0 {
+        0
+    } else if shift <= value.leading_zeros() as usize {
+        value << shift
+    } else {
+        u128::MAX
+    }
+}
+
+impl<V: SatVar, S: IncrementalSolver> Encoder<V, S> {
+    /// Approximately counts the distinct assignments of the projected variables
+    /// which can be extended to a model.
+    ///
+    /// With probability of at least `1 - delta` the result is within a factor of
+    /// `1 + epsilon` of the exact count.
+    /// Estimates which don't fit into `u128` saturate at `u128::MAX`.
+    /// Returns `None` if the solver failed.
+    ///
+    /// The search uses a fixed seed, see
+    /// [`approx_count_with_seed`](Encoder::approx_count_with_seed).
+    ///
+    /// # Panics
+    /// Panics if `epsilon` isn't positive or `delta` isn't between 0 and 1.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::AtMostK, Projection};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// encoder.add_constraint(AtMostK { k: 2, lits: 0..30 });
+    ///
+    /// let count = encoder.approx_count(Projection::all(), 0.8, 0.5).unwrap();
+    /// assert!((259..=838).contains(&count));
+    /// # }
+    /// ```
+    pub fn approx_count(
+        &mut self,
+        projection: Projection<V>,
+        epsilon: f64,
+        delta: f64,
+    ) -> Option<u128> {
+        self.approx_count_with_seed(projection, epsilon, delta, DEFAULT_SEED)
+    }
+
+    /// Like [`approx_count`](Encoder::approx_count), but the random hash functions
+    /// are derived from `seed`.
+    ///
+    /// This is an implementation of the ApproxMC algorithm.
+    /// The models are split into cells by random XOR constraints over the projected
+    /// variables until a cell is small enough to be enumerated.
+    /// The result is the median of the estimates of multiple rounds.
+    pub fn approx_count_with_seed(
+        &mut self,
+        projection: Projection<V>,
+        epsilon: f64,
+        delta: f64,
+        seed: u64,
+    ) -> Option<u128> {
+        assert!(epsilon > 0.0, "epsilon has to be positive");
+        assert!(
+            delta > 0.0 && delta < 1.0,
+            "delta has to be between 0 and 1"
+        );
+
+        let threshold = 1.0
+            + 9.84
+                * (1.0 + epsilon / (1.0 + epsilon))
+                * (1.0 + 1.0 / epsilon).powi(2);
+        let threshold = threshold.ceil() as usize;
+        let rounds = (17.0 * (3.0 / delta).log2()).ceil() as usize;
+
+        let mut cells = Cells {
+            projection: projection.resolve(&self.varmap),
+            guard: self.varmap.new_var(),
+            xors: Vec::new(),
+            models: Vec::new(),
+        };
+
+        let count = self.approx(&mut cells, threshold, rounds, seed);
+        self.backend.add_clause(iter::once(-cells.guard));
+        count
+    }
+
+    fn approx(
+        &mut self,
+        cells: &mut Cells,
+        threshold: usize,
+        rounds: usize,
+        seed: u64,
+    ) -> Option<u128> {
+        let count = self.cell_size(cells, 0, threshold)?;
+        if count < threshold {
+            return Some(count as u128);
+        }
+
+        let mut rng = Rng(seed);
+        let mut hashes = 1;
+        let mut estimates = Vec::with_capacity(rounds);
+
+        for _ in 0..rounds {
+            let estimate = self.estimate(cells, &mut rng, threshold, &mut hashes);
+
+            for xor in cells.xors.drain(..) {
+                self.backend.add_clause(iter::once(-xor.guard));
+            }
+            estimates.push(estimate?);
+        }
+
+        estimates.sort_unstable();
+        Some(estimates[estimates.len() / 2])
+    }
+
+    /// Searches the smallest number of hashes for which the cell has less than
+    /// `threshold` models, starting at the result of the previous round.
+    /// Hashes are added to `cells` as needed.
+    fn estimate(
+        &mut self,
+        cells: &mut Cells,
+        rng: &mut Rng,
+        threshold: usize,
+        hashes: &mut usize,
+    ) -> Option<u128> {
+        let max = cells.projection.len().max(1);
+        let mut cell_size = |encoder: &mut Self, m: usize| {
+            while cells.xors.len() < m {
+                let xor = encoder.add_random_xor(&cells.projection, rng);
+                cells.xors.push(xor);
+            }
+            encoder.cell_size(cells, m, threshold)
+        };
+
+        let mut m = (*hashes).max(1).min(max);
+        let mut cell = cell_size(self, m)?;
+
+        if cell >= threshold {
+            while cell >= threshold && m < max {
+                m += 1;
+                cell = cell_size(self, m)?;
+            }
+        } else {
+            while m > 1 {
+                let smaller = cell_size(self, m - 1)?;
+                if smaller >= threshold {
+                    break;
+                }
+                m -= 1;
+                cell = smaller;
+            }
+        }
+
+        *hashes = m;
+        Some(saturating_shl(cell as u128, m))
+    }
+
+    /// Counts at most `threshold` models of the cell selected by the first `m`
+    /// XOR constraints. New models are blocked and added to `cells`.
+    fn cell_size(
+        &mut self,
+        cells: &mut Cells,
+        m: usize,
+        threshold: usize,
+    ) -> Option<usize> {
+        let mut count = cells
+            .models
+            .iter()
+            .filter(|model| cells.contains(model, m))
+            .count();
+
+        let mut assumptions = vec![cells.guard];
+        assumptions.extend(cells.xors[..m].iter().map(|xor| xor.guard));
+
+        while count < threshold {
+            match self.solve_assuming(assumptions.clone()) {
+                SolveResult::Sat => {
+                    let backend = &mut self.backend;
+                    let model: Vec<_> =
+                        cells.projection.iter().map(|&v| backend.value(v)).collect();
+                    let blocking = cells
+                        .projection
+                        .iter()
+                        .zip(&model)
+                        .map(|(&v, &value)| if value { -v } else { v });
+                    backend.add_clause(blocking.chain(iter::once(-cells.guard)));
+
+                    cells.models.push(model);
+                    count += 1;
+                }
+                SolveResult::Unsat(_) => break,
+                _ => return None,
+            }
+        }
+
+        Some(count)
+    }
+
+    /// Encodes a random XOR constraint over `projection`.
+    fn add_random_xor(&mut self, projection: &[i32], rng: &mut Rng) -> Xor {
+        let guard = self.varmap.new_var();
+        let vars: Vec<_> = (0..projection.len()).filter(|_| rng.coin()).collect();
+        let odd = rng.coin();
+
+        let mut backend = GuardedBackend {
+            backend: &mut self.backend,
+            guards: &[guard],
+        };
+
+        let mut acc = match vars.first() {
+            Some(&i) => projection[i],
+            None => {
+                if odd {
+                    backend.add_clause(iter::empty());
+                }
+                return Xor { guard, vars, odd };
+            }
+        };
+
+        for &i in &vars[1..] {
+            let v = projection[i];
+            let t = self.varmap.new_var();
+
+            backend.add_clause([-t, acc, v].iter().copied());
+            backend.add_clause([-t, -acc, -v].iter().copied());
+            backend.add_clause([t, -acc, v].iter().copied());
+            backend.add_clause([t, acc, -v].iter().copied());
+
+            acc = t;
+        }
+
+        backend.add_clause(iter::once(if odd { acc } else { -acc }));
+        Xor { guard, vars, odd }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::saturating_shl;
+    use crate::{
+        backend::CdclEncoder,
+        constraints::{AtMostK, ExactlyK},
+        Projection,
+    };
+
+    #[test]
+    fn small_counts_are_exact() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(ExactlyK { k: 2, lits: 0..8 });
+
+        let count = encoder.approx_count(Projection::all(), 0.8, 0.2);
+        assert_eq!(count, Some(28));
+    }
+
+    #[test]
+    fn approx_count_is_reproducible() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(AtMostK { k: 3, lits: 0..16 });
+        // 1 + 16 + 120 + 560
+        let exact = 697.0;
+
+        let count = |encoder: &mut CdclEncoder<u32>, seed| {
+            let projection = Projection::all();
+            encoder
+                .approx_count_with_seed(projection, 0.8, 0.5, seed)
+                .unwrap()
+        };
+
+        let first = count(&mut encoder, 7);
+        assert!(first as f64 >= exact / 1.8 && first as f64 <= exact * 1.8);
+        assert_eq!(count(&mut encoder, 7), first);
+
+        // The hash constraints are removed again.
+        assert_eq!(encoder.models(Projection::all()).count(), 697);
+    }
+
+    #[test]
+    fn estimates_saturate() {
+        assert_eq!(saturating_shl(1, 127), 1 << 127);
+        assert_eq!(saturating_shl(3, 126), 3 << 126);
+        assert_eq!(saturating_shl(3, 127), u128::MAX);
+        assert_eq!(saturating_shl(5, 200), u128::MAX);
+        assert_eq!(saturating_shl(0, 200), 0);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{Encoder, IncrementalSolver, Lit, SatVar, SolveResult};
+
+impl<V: SatVar, S: IncrementalSolver> Encoder<V, S> {
+    /// Returns the literals of `vars` which are true in every model.
+    ///
+    /// The problem is solved once, afterwards each remaining candidate is flipped
+    /// under an assumption. Every model found on the way removes all candidates
+    /// it disagrees with.
+    /// Returns `None` if the problem is unsatisfiable or the solver failed.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::{AtMostK, Or}, Lit};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// encoder.add_constraint(AtMostK { k: 1, lits: ["a", "b", "c"].iter().copied() });
+    /// encoder.add_constraint(Or(vec![Lit::Pos("a"), Lit::Pos("d")].into_iter()));
+    /// encoder.add_constraint(Lit::Pos("b"));
+    ///
+    /// let backbone = encoder.backbone(vec!["a", "b", "c", "d"]).unwrap();
+    /// assert_eq!(
+    ///     backbone,
+    ///     vec![Lit::Neg("a"), Lit::Pos("b"), Lit::Neg("c"), Lit::Pos("d")]
+    /// );
+    /// # }
+    /// ```
+    pub fn backbone<I>(&mut self, vars: I) -> Option<Vec<Lit<V>>>
+    where
+        I: IntoIterator<Item = V>,
+    {
+        let vars: Vec<_> = vars
+            .into_iter()
+            .map(|v| self.varmap.add_var(Lit::Pos(v)))
+            .collect();
+
+        if self.solve_assuming(Vec::new()) != SolveResult::Sat {
+            return None;
+        }
+
+        let backend = &mut self.backend;
+        let mut candidates: Vec<_> = vars
+            .iter()
+            .map(|&v| if backend.value(v) { v } else { -v })
+            .collect();
+
+        // Backbone literals which were already proven are assumed in later calls.
+        let mut proven = Vec::new();
+        let mut i = 0;
+
+        while i < candidates.len() {
+            let candidate = candidates[i];
+
+            let mut assumptions = proven.clone();
+            assumptions.push(-candidate);
+
+            match self.solve_assuming(assumptions) {
+                SolveResult::Sat => {
+                    let backend = &mut self.backend;
+                    candidates.retain(|&c| backend.value(c));
+                    // The flipped candidate is gone, so `i` points to the next one.
+                }
+                SolveResult::Unsat(_) => {
+                    proven.push(candidate);
+                    i += 1;
+                }
+                SolveResult::Interrupted | SolveResult::Unknown => return None,
+            }
+        }
+
+        let backbone = candidates
+            .into_iter()
+            .map(|lit| self.varmap.lookup(lit).unwrap())
+            .collect();
+        Some(backbone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backend::CdclEncoder,
+        constraints::{AtMostK, ExactlyK},
+        Lit,
+    };
+
+    #[test]
+    fn backbone_of_cardinality() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(ExactlyK { k: 2, lits: 0..5 });
+        encoder.add_constraint(AtMostK { k: 1, lits: 0..3 });
+        encoder.add_constraint(Lit::Pos(1));
+
+        // One of 3 and 4 has to be true, so neither is forced.
+        let backbone = encoder.backbone(0..6).unwrap();
+        assert_eq!(backbone, vec![Lit::Neg(0), Lit::Pos(1), Lit::Neg(2)]);
+
+        let guard = encoder.add_guarded_constraint(Lit::Neg(3));
+        let backbone = encoder.backbone(2..5).unwrap();
+        assert_eq!(backbone, vec![Lit::Neg(2), Lit::Neg(3), Lit::Pos(4)]);
+
+        encoder.add_constraint(Lit::Neg(4));
+        assert_eq!(encoder.backbone(0..5), None);
+
+        encoder.retire_guard(guard);
+        assert_eq!(encoder.backbone(3..5), Some(vec![Lit::Pos(3), Lit::Neg(4)]));
+    }
+}
@@ -1,16 +1,63 @@
-use std::fmt;
+use std::{
+    fmt, iter,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
-use crate::{Backend, Encoder, SolveResult, Solver};
+use super::Recorder;
+use crate::{
+    Backend, Encoder, IncrementalSolver, LimitedSolver, SolveLimits, SolveResult,
+    Solver,
+};
 
 /// Encoder using the CaDiCal SAT solver.
-pub type CadicalEncoder<V> = Encoder<V, cadical::Solver>;
+pub type CadicalEncoder<V> = Encoder<V, cadical::Solver<LimitCallbacks>>;
 
-impl Backend for cadical::Solver {
+/// Encoder using the CaDiCal SAT solver, which keeps a copy of all clauses.
+///
+/// CaDiCaL doesn't return the clauses added to it, so the solver is wrapped in a
+/// [`Recorder`] to support [`ClauseDatabase`](crate::ClauseDatabase) features like
+/// [`count_models`](Encoder::count_models).
+pub type RecordingCadicalEncoder<V> =
+    Encoder<V, Recorder<cadical::Solver<LimitCallbacks>>>;
+
+/// CaDiCaL callbacks which stop the search once the time limit of the
+/// [`SolveLimits`] is reached or the terminate flag is set.
+#[derive(Debug, Clone, Default)]
+pub struct LimitCallbacks {
+    started: Option<Instant>,
+    time: Option<Duration>,
+    terminate: Option<Arc<AtomicBool>>,
+}
+
+impl cadical::Callbacks for LimitCallbacks {
+    fn started(&mut self) {
+        self.started = Some(Instant::now());
+    }
+
+    fn terminate(&mut self) -> bool {
+        let timeout = match (self.started, self.time) {
+            (Some(started), Some(time)) => started.elapsed() >= time,
+            _ => false,
+        };
+        let terminated = self
+            .terminate
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed));
+
+        timeout || terminated
+    }
+}
+
+impl<C: cadical::Callbacks> Backend for cadical::Solver<C> {
     fn add_clause<I>(&mut self, lits: I)
     where
         I: Iterator<Item = i32>,
     {
-        self.add_clause(lits.into_iter());
+        self.add_clause(lits);
     }
 
     fn add_debug_info<D: fmt::Debug>(&mut self, debug: D) {
@@ -22,7 +69,7 @@ impl Backend for cadical::Solver {
     }
 }
 
-impl Solver for cadical::Solver {
+impl<C: cadical::Callbacks> Solver for cadical::Solver<C> {
     fn solve(&mut self) -> SolveResult {
         match self.solve() {
             Some(true) => SolveResult::Sat,
@@ -34,4 +81,163 @@ impl Solver for cadical::Solver {
     fn value(&mut self, var: i32) -> bool {
         self.value(var).unwrap_or(true)
     }
+
+    fn guarded_solve(&mut self, guards: &[i32]) -> SolveResult {
+        self.assumption_solve(guards.iter().copied())
+    }
+}
+
+impl<C: cadical::Callbacks> IncrementalSolver for cadical::Solver<C> {
+    fn assumption_solve<I>(&mut self, assumptions: I) -> SolveResult
+    where
+        I: Iterator<Item = i32>,
+    {
+        let assumptions: Vec<_> = assumptions.collect();
+
+        match self.solve_with(assumptions.iter().copied(), iter::empty()) {
+            Some(true) => SolveResult::Sat,
+            Some(false) => {
+                let core = assumptions
+                    .into_iter()
+                    .filter(|&lit| self.failed(lit))
+                    .collect();
+                SolveResult::Unsat(Some(core))
+            }
+            None => SolveResult::Unknown,
+        }
+    }
+}
+
+/// CaDiCaL resets the conflict and decision limits after each solve call.
+/// The time limit and the terminate flag are checked by [`LimitCallbacks`].
+impl LimitedSolver for cadical::Solver<LimitCallbacks> {
+    fn set_limits(&mut self, limits: SolveLimits) {
+        let clamp =
+            |limit: Option<u64>| limit.map_or(-1, |l| l.min(i32::MAX as u64) as i32);
+
+        self.set_limit("conflicts", clamp(limits.conflicts))
+            .unwrap();
+        self.set_limit("decisions", clamp(limits.decisions))
+            .unwrap();
+
+        let callbacks = LimitCallbacks {
+            started: None,
+            time: limits.time,
+            terminate: limits.terminate,
+        };
+        let enabled = callbacks.time.is_some() || callbacks.terminate.is_some();
+        self.set_callbacks(enabled.then_some(callbacks));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constraints::{AtLeastK, AtMostK, ExactlyK},
+        AssumptionSolveResult, AssumptionSolver, Lit, Projection, SolveOutcome,
+    };
+
+    #[test]
+    fn failed_assumptions() {
+        let mut solver = cadical::Solver::<LimitCallbacks>::new();
+        solver.add_clause([-1, 2].iter().copied());
+        solver.add_clause([-2, 3].iter().copied());
+
+        match solver.assumption_solve([4, 1, -3].iter().copied()) {
+            SolveResult::Unsat(Some(mut core)) => {
+                core.sort_unstable();
+                assert_eq!(core, vec![-3, 1]);
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        assert_eq!(
+            solver.assumption_solve([1].iter().copied()),
+            SolveResult::Sat
+        );
+    }
+
+    #[test]
+    fn assumed_constraints_core() {
+        let mut encoder = CadicalEncoder::<u32>::new();
+        encoder.add_constraint(AtMostK { k: 2, lits: 0..6 });
+
+        let assumptions = vec![
+            AtLeastK { k: 2, lits: 0..3 },
+            AtLeastK { k: 1, lits: 6..8 },
+            AtLeastK { k: 1, lits: 3..6 },
+        ];
+
+        match encoder.assumption_solve(assumptions, false) {
+            AssumptionSolveResult::Unsat(Some(failed)) => {
+                let mut starts: Vec<_> =
+                    failed.iter().map(|c| c.lits.start).collect();
+                starts.sort_unstable();
+                assert_eq!(starts, vec![0, 3]);
+            }
+            _ => panic!("assumptions should be unsatisfiable"),
+        }
+
+        let sat =
+            encoder.assumption_solve(vec![AtLeastK { k: 2, lits: 0..3 }], false);
+        assert!(matches!(sat, AssumptionSolveResult::Sat(_)));
+    }
+
+    #[test]
+    fn committed_assumptions_are_scoped() {
+        let mut encoder = CadicalEncoder::<u32>::new();
+        encoder.add_constraint(AtLeastK { k: 1, lits: 0..2 });
+
+        encoder.push();
+        let sat = encoder.assumption_solve(vec![AtMostK { k: 0, lits: 0..1 }], true);
+        assert!(matches!(sat, AssumptionSolveResult::Sat(_)));
+        assert!(!encoder.solve().unwrap()[0]);
+        encoder.pop();
+
+        // The committed constraint is removed with its scope.
+        encoder.add_constraint(AtLeastK { k: 1, lits: 0..1 });
+        assert!(encoder.solve().is_some());
+    }
+
+    #[test]
+    fn count_and_propagate() {
+        let mut encoder = RecordingCadicalEncoder::<u32>::new();
+        encoder.add_constraint(ExactlyK { k: 1, lits: 0..4 });
+
+        assert_eq!(encoder.count_models(Projection::all()), 4);
+        assert_eq!(
+            encoder.propagate(&[Lit::Pos(0)]).unwrap(),
+            vec![Lit::Neg(1), Lit::Neg(2), Lit::Neg(3)]
+        );
+    }
+
+    #[test]
+    fn terminate_flag_interrupts() {
+        let mut encoder = CadicalEncoder::<(u32, u32)>::new();
+
+        // Pigeonhole problem: 9 pigeons don't fit in 8 holes.
+        for pigeon in 0..9 {
+            encoder.add_constraint(ExactlyK {
+                k: 1,
+                lits: (0..8).map(|hole| (pigeon, hole)),
+            });
+        }
+        for hole in 0..8 {
+            encoder.add_constraint(AtMostK {
+                k: 1,
+                lits: (0..9).map(|pigeon| (pigeon, hole)),
+            });
+        }
+
+        let flag = Arc::new(AtomicBool::new(true));
+        let limits = SolveLimits::new().terminate(Arc::clone(&flag));
+        assert!(matches!(
+            encoder.solve_with_limits(limits),
+            SolveOutcome::Interrupted
+        ));
+
+        // The flag only applies to the call it was passed to.
+        assert!(encoder.solve().is_none());
+    }
 }
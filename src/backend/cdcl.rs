@@ -0,0 +1,1279 @@
+use std::{
+    fmt,
+    ops::Not,
+    sync::atomic::{AtomicBool, Ordering},
+    sync::Arc,
+    time::Instant,
+};
+
+use crate::{
+    Backend, ClauseDatabase, Encoder, IncrementalSolver, LimitedSolver, SolveLimits,
+    SolveResult, SolveStats, Solver,
+};
+
+/// Encoder using the built-in [`Cdcl`] SAT solver.
+pub type CdclEncoder<V> = Encoder<V, Cdcl>;
+
+const RESTART_BASE: u64 = 100;
+const VAR_DECAY: f64 = 0.95;
+const CLAUSE_DECAY: f64 = 0.999;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Lit(u32);
+
+impl Lit {
+    fn from_dimacs(lit: i32) -> Self {
+        assert!(lit != 0, "0 is not a valid literal");
+        let var = lit.unsigned_abs() - 1;
+        Lit(2 * var + (lit < 0) as u32)
+    }
+
+    fn to_dimacs(self) -> i32 {
+        let var = (self.0 >> 1) as i32 + 1;
+        if self.is_neg() {
+            -var
+        } else {
+            var
+        }
+    }
+
+    fn var(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    fn is_neg(self) -> bool {
+        self.0 & 1 == 1
+    }
+}
+
+impl Not for Lit {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Lit(self.0 ^ 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Value {
+    True,
+    False,
+    Undef,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Watcher {
+    clause: u32,
+    blocker: Lit,
+}
+
+#[derive(Debug, Clone)]
+struct Clause {
+    lits: Vec<Lit>,
+    learnt: bool,
+    deleted: bool,
+    lbd: u32,
+    activity: f64,
+}
+
+fn lit_value(assigns: &[Value], lit: Lit) -> Value {
+    match assigns[lit.var()] {
+        Value::Undef => Value::Undef,
+        Value::True if lit.is_neg() => Value::False,
+        Value::False if !lit.is_neg() => Value::False,
+        _ => Value::True,
+    }
+}
+
+/// Outcome of a single restart-bounded search.
+enum Search {
+    Sat,
+    Unsat,
+    Restart,
+    Stop(SolveResult),
+}
+
+/// Limits of the running solve call, converted to absolute values.
+#[derive(Default)]
+struct Budget {
+    deadline: Option<Instant>,
+    conflicts: Option<u64>,
+    decisions: Option<u64>,
+    terminate: Option<Arc<AtomicBool>>,
+}
+
+/// Binary max-heap of variables ordered by their activity.
+#[derive(Debug, Clone, Default)]
+struct VarOrder {
+    heap: Vec<usize>,
+    position: Vec<Option<usize>>,
+}
+
+impl VarOrder {
+    fn grow(&mut self, vars: usize) {
+        self.position.resize(vars, None);
+    }
+
+    fn contains(&self, var: usize) -> bool {
+        self.position[var].is_some()
+    }
+
+    fn insert(&mut self, var: usize, activity: &[f64]) {
+        if !self.contains(var) {
+            self.position[var] = Some(self.heap.len());
+            self.heap.push(var);
+            self.sift_up(self.heap.len() - 1, activity);
+        }
+    }
+
+    fn increased(&mut self, var: usize, activity: &[f64]) {
+        if let Some(pos) = self.position[var] {
+            self.sift_up(pos, activity);
+        }
+    }
+
+    fn pop(&mut self, activity: &[f64]) -> Option<usize> {
+        let top = *self.heap.first()?;
+        let last = self.heap.pop().unwrap();
+        self.position[top] = None;
+
+        if !self.heap.is_empty() {
+            self.heap[0] = last;
+            self.position[last] = Some(0);
+            self.sift_down(0, activity);
+        }
+
+        Some(top)
+    }
+
+    fn sift_up(&mut self, mut pos: usize, activity: &[f64]) {
+        let var = self.heap[pos];
+
+        while pos > 0 {
+            let parent = (pos - 1) / 2;
+            if activity[self.heap[parent]] >= activity[var] {
+                break;
+            }
+            self.heap[pos] = self.heap[parent];
+            self.position[self.heap[pos]] = Some(pos);
+            pos = parent;
+        }
+
+        self.heap[pos] = var;
+        self.position[var] = Some(pos);
+    }
+
+    fn sift_down(&mut self, mut pos: usize, activity: &[f64]) {
+        let var = self.heap[pos];
+
+        loop {
+            let left = 2 * pos + 1;
+            if left >= self.heap.len() {
+                break;
+            }
+            let right = left + 1;
+            let child = if right < self.heap.len()
+                && activity[self.heap[right]] > activity[self.heap[left]]
+            {
+                right
+            } else {
+                left
+            };
+
+            if activity[self.heap[child]] <= activity[var] {
+                break;
+            }
+            self.heap[pos] = self.heap[child];
+            self.position[self.heap[pos]] = Some(pos);
+            pos = child;
+        }
+
+        self.heap[pos] = var;
+        self.position[var] = Some(pos);
+    }
+}
+
+/// Pure Rust CDCL SAT solver.
+///
+/// It uses two watched literals for unit propagation, learns 1UIP clauses, picks
+/// decision variables by activity (VSIDS), restarts following the Luby sequence and
+/// periodically removes learnt clauses with a high literal block distance.
+/// Solving under assumptions is supported through [`IncrementalSolver`], returning
+/// the failed assumptions as core if the problem is unsatisfiable.
+///
+/// # Example
+/// ```rust
+/// # use satoxid::{backend::CdclEncoder, Lit};
+/// # fn main() {
+/// let mut encoder = CdclEncoder::new();
+/// encoder.add_constraint(Lit::Pos("a"));
+/// encoder.add_constraint(Lit::Neg("b"));
+///
+/// let model = encoder.solve().unwrap();
+/// assert!(model["a"]);
+/// assert!(!model["b"]);
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct Cdcl {
+    clauses: Vec<Clause>,
+    watches: Vec<Vec<Watcher>>,
+    learnts: usize,
+    max_learnts: f64,
+    clause_inc: f64,
+
+    assigns: Vec<Value>,
+    level: Vec<u32>,
+    reason: Vec<Option<u32>>,
+    phase: Vec<bool>,
+    seen: Vec<bool>,
+    /// Variables which don't occur in any clause and are never decided.
+    eliminated: Vec<bool>,
+    activity: Vec<f64>,
+    var_inc: f64,
+    order: VarOrder,
+
+    trail: Vec<Lit>,
+    trail_lim: Vec<usize>,
+    queue_head: usize,
+
+    assumptions: Vec<Lit>,
+    failed: Vec<i32>,
+    model: Vec<bool>,
+    inconsistent: bool,
+    /// Propagate only the original clauses.
+    skip_learnts: bool,
+
+    conflicts: u64,
+    decisions: u64,
+    propagations: u64,
+    restarts: u64,
+
+    limits: SolveLimits,
+    stats: SolveStats,
+    /// Length of the trail at the last simplification.
+    simplified: usize,
+}
+
+impl Cdcl {
+    /// Create new `Cdcl` solver.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of variables known to the solver.
+    pub fn num_vars(&self) -> usize {
+        self.assigns.len()
+    }
+
+    fn grow_to(&mut self, vars: usize) {
+        if vars <= self.num_vars() {
+            return;
+        }
+
+        let old = self.num_vars();
+        self.assigns.resize(vars, Value::Undef);
+        self.level.resize(vars, 0);
+        self.reason.resize(vars, None);
+        self.phase.resize(vars, false);
+        self.seen.resize(vars, false);
+        self.eliminated.resize(vars, false);
+        self.activity.resize(vars, 0.0);
+        self.watches.resize(2 * vars, Vec::new());
+        self.order.grow(vars);
+
+        for var in old..vars {
+            self.order.insert(var, &self.activity);
+        }
+    }
+
+    fn value_of(&self, lit: Lit) -> Value {
+        lit_value(&self.assigns, lit)
+    }
+
+    fn decision_level(&self) -> u32 {
+        self.trail_lim.len() as u32
+    }
+
+    fn enqueue(&mut self, lit: Lit, reason: Option<u32>) {
+        let var = lit.var();
+        self.assigns[var] = if lit.is_neg() {
+            Value::False
+        } else {
+            Value::True
+        };
+        self.level[var] = self.decision_level();
+        self.reason[var] = reason;
+        self.trail.push(lit);
+    }
+
+    fn new_decision_level(&mut self) {
+        self.trail_lim.push(self.trail.len());
+    }
+
+    fn cancel_until(&mut self, level: u32) {
+        if self.decision_level() <= level {
+            return;
+        }
+
+        let lim = self.trail_lim[level as usize];
+        for i in (lim..self.trail.len()).rev() {
+            let lit = self.trail[i];
+            let var = lit.var();
+            self.assigns[var] = Value::Undef;
+            self.reason[var] = None;
+            self.phase[var] = !lit.is_neg();
+            self.order.insert(var, &self.activity);
+        }
+
+        self.trail.truncate(lim);
+        self.trail_lim.truncate(level as usize);
+        self.queue_head = lim;
+    }
+
+    fn attach(&mut self, clause: u32) {
+        let lits = &self.clauses[clause as usize].lits;
+        let (l0, l1) = (lits[0], lits[1]);
+        self.watches[(!l0).index()].push(Watcher {
+            clause,
+            blocker: l1,
+        });
+        self.watches[(!l1).index()].push(Watcher {
+            clause,
+            blocker: l0,
+        });
+    }
+
+    fn push_clause(&mut self, lits: Vec<Lit>, learnt: bool, lbd: u32) -> u32 {
+        let idx = self.clauses.len() as u32;
+        self.clauses.push(Clause {
+            lits,
+            learnt,
+            deleted: false,
+            lbd,
+            activity: 0.0,
+        });
+        if learnt {
+            self.learnts += 1;
+        }
+        self.attach(idx);
+        idx
+    }
+
+    /// Propagates all enqueued literals.
+    /// Returns the conflicting clause if one is found.
+    fn propagate(&mut self) -> Option<u32> {
+        let mut conflict = None;
+
+        while self.queue_head < self.trail.len() {
+            let p = self.trail[self.queue_head];
+            self.queue_head += 1;
+            self.propagations += 1;
+
+            let false_lit = !p;
+            let mut watchers = std::mem::take(&mut self.watches[p.index()]);
+            let (mut i, mut j) = (0, 0);
+
+            while i < watchers.len() {
+                let w = watchers[i];
+                i += 1;
+
+                let c = &self.clauses[w.clause as usize];
+                if c.deleted {
+                    continue;
+                }
+                if c.learnt && self.skip_learnts {
+                    watchers[j] = w;
+                    j += 1;
+                    continue;
+                }
+
+                let assigns = &self.assigns;
+
+                if lit_value(assigns, w.blocker) == Value::True {
+                    watchers[j] = w;
+                    j += 1;
+                    continue;
+                }
+
+                let clause = &mut self.clauses[w.clause as usize].lits;
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+
+                let first = clause[0];
+                let watcher = Watcher {
+                    clause: w.clause,
+                    blocker: first,
+                };
+
+                if first != w.blocker && lit_value(assigns, first) == Value::True {
+                    watchers[j] = watcher;
+                    j += 1;
+                    continue;
+                }
+
+                let replacement = (2..clause.len())
+                    .find(|&k| lit_value(assigns, clause[k]) != Value::False);
+
+                if let Some(k) = replacement {
+                    clause.swap(1, k);
+                    let watch = !clause[1];
+                    self.watches[watch.index()].push(watcher);
+                    continue;
+                }
+
+                watchers[j] = watcher;
+                j += 1;
+
+                if self.value_of(first) == Value::False {
+                    conflict = Some(w.clause);
+                    self.queue_head = self.trail.len();
+                    while i < watchers.len() {
+                        watchers[j] = watchers[i];
+                        i += 1;
+                        j += 1;
+                    }
+                } else {
+                    self.enqueue(first, Some(w.clause));
+                }
+            }
+
+            watchers.truncate(j);
+            self.watches[p.index()] = watchers;
+
+            if conflict.is_some() {
+                break;
+            }
+        }
+
+        conflict
+    }
+
+    fn bump_var(&mut self, var: usize) {
+        self.activity[var] += self.var_inc;
+
+        if self.activity[var] > 1e100 {
+            for a in self.activity.iter_mut() {
+                *a *= 1e-100;
+            }
+            self.var_inc *= 1e-100;
+        }
+
+        self.order.increased(var, &self.activity);
+    }
+
+    fn bump_clause(&mut self, clause: u32) {
+        let c = &mut self.clauses[clause as usize];
+        c.activity += self.clause_inc;
+
+        if c.activity > 1e20 {
+            for c in self.clauses.iter_mut().filter(|c| c.learnt) {
+                c.activity *= 1e-20;
+            }
+            self.clause_inc *= 1e-20;
+        }
+    }
+
+    fn compute_lbd(&self, lits: &[Lit]) -> u32 {
+        let mut levels: Vec<_> = lits.iter().map(|l| self.level[l.var()]).collect();
+        levels.sort_unstable();
+        levels.dedup();
+        levels.len() as u32
+    }
+
+    /// Analyzes a conflict and returns a 1UIP learnt clause, with the asserting
+    /// literal at the first position, and the level to backtrack to.
+    fn analyze(&mut self, mut conflict: u32) -> (Vec<Lit>, u32) {
+        let mut learnt = vec![Lit(0)];
+        let mut path_count = 0;
+        let mut p: Option<Lit> = None;
+        let mut index = self.trail.len();
+
+        loop {
+            if self.clauses[conflict as usize].learnt {
+                self.bump_clause(conflict);
+            }
+
+            let skip = if p.is_some() { 1 } else { 0 };
+            for k in skip..self.clauses[conflict as usize].lits.len() {
+                let q = self.clauses[conflict as usize].lits[k];
+                let var = q.var();
+
+                if !self.seen[var] && self.level[var] > 0 {
+                    self.bump_var(var);
+                    self.seen[var] = true;
+
+                    if self.level[var] >= self.decision_level() {
+                        path_count += 1;
+                    } else {
+                        learnt.push(q);
+                    }
+                }
+            }
+
+            loop {
+                index -= 1;
+                if self.seen[self.trail[index].var()] {
+                    break;
+                }
+            }
+
+            let lit = self.trail[index];
+            p = Some(lit);
+            self.seen[lit.var()] = false;
+            path_count -= 1;
+
+            if path_count == 0 {
+                break;
+            }
+            conflict = self.reason[lit.var()].expect("implied literal has a reason");
+        }
+
+        learnt[0] = !p.unwrap();
+
+        // Remove literals whose reason is already covered by the learnt clause.
+        let redundant: Vec<bool> = learnt
+            .iter()
+            .map(|&l| match self.reason[l.var()] {
+                Some(r) => self.clauses[r as usize].lits[1..]
+                    .iter()
+                    .all(|q| self.seen[q.var()] || self.level[q.var()] == 0),
+                None => false,
+            })
+            .collect();
+
+        for l in &learnt {
+            self.seen[l.var()] = false;
+        }
+
+        let mut learnt: Vec<_> = learnt
+            .into_iter()
+            .zip(redundant)
+            .enumerate()
+            .filter(|&(i, (_, redundant))| i == 0 || !redundant)
+            .map(|(_, (l, _))| l)
+            .collect();
+
+        let backtrack = if learnt.len() == 1 {
+            0
+        } else {
+            let max = (1..learnt.len())
+                .max_by_key(|&i| self.level[learnt[i].var()])
+                .unwrap();
+            learnt.swap(1, max);
+            self.level[learnt[1].var()]
+        };
+
+        (learnt, backtrack)
+    }
+
+    /// Computes the set of assumptions responsible for `p` being false.
+    fn analyze_final(&mut self, p: Lit) {
+        self.failed.clear();
+        self.failed.push((!p).to_dimacs());
+
+        if self.decision_level() == 0 {
+            return;
+        }
+
+        self.seen[p.var()] = true;
+        self.collect_failed();
+        self.seen[p.var()] = false;
+    }
+
+    /// Collects the assumptions which lead to the conflicting `clause` into
+    /// `failed`.
+    fn analyze_conflict(&mut self, clause: u32) {
+        self.failed.clear();
+
+        for k in 0..self.clauses[clause as usize].lits.len() {
+            let var = self.clauses[clause as usize].lits[k].var();
+            if self.level[var] > 0 {
+                self.seen[var] = true;
+            }
+        }
+        self.collect_failed();
+    }
+
+    /// Adds the decisions the variables marked as seen depend on to `failed` and
+    /// clears the marks.
+    fn collect_failed(&mut self) {
+        for i in (self.trail_lim[0]..self.trail.len()).rev() {
+            let lit = self.trail[i];
+            let var = lit.var();
+
+            if !self.seen[var] {
+                continue;
+            }
+
+            match self.reason[var] {
+                None => self.failed.push(lit.to_dimacs()),
+                Some(r) => {
+                    for k in 1..self.clauses[r as usize].lits.len() {
+                        let q = self.clauses[r as usize].lits[k];
+                        if self.level[q.var()] > 0 {
+                            self.seen[q.var()] = true;
+                        }
+                    }
+                }
+            }
+            self.seen[var] = false;
+        }
+    }
+
+    fn is_locked(&self, clause: u32) -> bool {
+        let first = self.clauses[clause as usize].lits[0];
+        self.reason[first.var()] == Some(clause)
+            && self.value_of(first) == Value::True
+    }
+
+    /// Assigns each assumption on its own decision level and propagates it.
+    /// Returns false and fills `failed` on a conflict.
+    fn assume(&mut self, assumptions: &[i32]) -> bool {
+        for &a in assumptions {
+            self.grow_to(a.unsigned_abs() as usize);
+            let p = Lit::from_dimacs(a);
+
+            match self.value_of(p) {
+                Value::True => {}
+                Value::False => {
+                    self.analyze_final(!p);
+                    return false;
+                }
+                Value::Undef => {
+                    self.new_decision_level();
+                    self.enqueue(p, None);
+                    if let Some(conflict) = self.propagate() {
+                        self.analyze_conflict(conflict);
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Deletes all clauses which are satisfied at decision level 0 and eliminates
+    /// the variables which don't occur anymore.
+    fn simplify(&mut self) {
+        if self.trail.len() == self.simplified {
+            return;
+        }
+
+        for &lit in &self.trail {
+            self.reason[lit.var()] = None;
+        }
+
+        let assigns = &self.assigns;
+        for clause in &mut self.clauses {
+            if !clause.deleted
+                && clause
+                    .lits
+                    .iter()
+                    .any(|&l| lit_value(assigns, l) == Value::True)
+            {
+                clause.deleted = true;
+                clause.lits = Vec::new();
+                if clause.learnt {
+                    self.learnts -= 1;
+                }
+            }
+        }
+
+        let mut occurs = vec![false; self.num_vars()];
+        for clause in &self.clauses {
+            for lit in &clause.lits {
+                occurs[lit.var()] = true;
+            }
+        }
+        for (var, occurs) in occurs.into_iter().enumerate() {
+            self.eliminated[var] = !occurs && self.assigns[var] == Value::Undef;
+        }
+
+        self.simplified = self.trail.len();
+    }
+
+    /// Deletes half of the learnt clauses, preferring ones with a high LBD and low
+    /// activity.
+    fn reduce_learnts(&mut self) {
+        let mut candidates: Vec<u32> = (0..self.clauses.len() as u32)
+            .filter(|&c| {
+                let clause = &self.clauses[c as usize];
+                clause.learnt && !clause.deleted && clause.lbd > 2
+            })
+            .filter(|&c| !self.is_locked(c))
+            .collect();
+
+        candidates.sort_by(|&a, &b| {
+            let (a, b) = (&self.clauses[a as usize], &self.clauses[b as usize]);
+            b.lbd
+                .cmp(&a.lbd)
+                .then(a.activity.partial_cmp(&b.activity).unwrap())
+        });
+
+        for &c in candidates.iter().take(candidates.len() / 2) {
+            let clause = &mut self.clauses[c as usize];
+            clause.deleted = true;
+            clause.lits = Vec::new();
+            self.learnts -= 1;
+        }
+    }
+
+    fn pick_branch_lit(&mut self) -> Option<Lit> {
+        while let Some(var) = self.order.pop(&self.activity) {
+            if self.assigns[var] == Value::Undef && !self.eliminated[var] {
+                return Some(Lit(2 * var as u32 + !self.phase[var] as u32));
+            }
+        }
+        None
+    }
+
+    fn exhausted(&self, budget: &Budget) -> Option<SolveResult> {
+        let terminated = budget
+            .terminate
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed));
+        let timeout = budget.deadline.is_some_and(|d| Instant::now() >= d);
+
+        if terminated || timeout {
+            Some(SolveResult::Interrupted)
+        } else if budget.conflicts.is_some_and(|c| self.conflicts >= c)
+            || budget.decisions.is_some_and(|d| self.decisions >= d)
+        {
+            Some(SolveResult::Unknown)
+        } else {
+            None
+        }
+    }
+
+    fn search(&mut self, conflict_budget: u64, budget: &Budget) -> Search {
+        let mut conflicts = 0;
+
+        loop {
+            if let Some(conflict) = self.propagate() {
+                self.conflicts += 1;
+                conflicts += 1;
+
+                if self.decision_level() == 0 {
+                    return Search::Unsat;
+                }
+
+                let (learnt, backtrack) = self.analyze(conflict);
+                self.cancel_until(backtrack);
+
+                if learnt.len() == 1 {
+                    self.enqueue(learnt[0], None);
+                } else {
+                    let lbd = self.compute_lbd(&learnt);
+                    let asserting = learnt[0];
+                    let clause = self.push_clause(learnt, true, lbd);
+                    self.enqueue(asserting, Some(clause));
+                }
+
+                self.var_inc /= VAR_DECAY;
+                self.clause_inc /= CLAUSE_DECAY;
+                continue;
+            }
+
+            if let Some(result) = self.exhausted(budget) {
+                self.cancel_until(0);
+                return Search::Stop(result);
+            }
+
+            if conflicts >= conflict_budget {
+                self.cancel_until(0);
+                return Search::Restart;
+            }
+
+            if self.learnts as f64 - self.trail.len() as f64 >= self.max_learnts {
+                self.reduce_learnts();
+            }
+
+            let mut next = None;
+            while (self.decision_level() as usize) < self.assumptions.len() {
+                let p = self.assumptions[self.decision_level() as usize];
+                match self.value_of(p) {
+                    Value::True => self.new_decision_level(),
+                    Value::False => {
+                        self.analyze_final(!p);
+                        return Search::Unsat;
+                    }
+                    Value::Undef => {
+                        next = Some(p);
+                        break;
+                    }
+                }
+            }
+
+            let next = match next {
+                Some(p) => p,
+                None => {
+                    self.decisions += 1;
+                    match self.pick_branch_lit() {
+                        Some(p) => p,
+                        None => return Search::Sat,
+                    }
+                }
+            };
+
+            self.new_decision_level();
+            self.enqueue(next, None);
+        }
+    }
+
+    fn solve_internal(&mut self, assumptions: Vec<i32>) -> SolveResult {
+        let start = Instant::now();
+        let (conflicts, decisions) = (self.conflicts, self.decisions);
+        let (propagations, restarts) = (self.propagations, self.restarts);
+
+        let result = self.solve_limited(assumptions);
+
+        self.stats = SolveStats {
+            time: start.elapsed(),
+            conflicts: self.conflicts - conflicts,
+            decisions: self.decisions - decisions,
+            propagations: self.propagations - propagations,
+            restarts: self.restarts - restarts,
+        };
+        result
+    }
+
+    fn solve_limited(&mut self, assumptions: Vec<i32>) -> SolveResult {
+        self.model.clear();
+        self.failed.clear();
+
+        if self.inconsistent {
+            return SolveResult::Unsat(Some(Vec::new()));
+        }
+
+        self.simplify();
+
+        for &a in &assumptions {
+            self.grow_to(a.unsigned_abs() as usize);
+        }
+        self.assumptions = assumptions.into_iter().map(Lit::from_dimacs).collect();
+
+        if self.max_learnts == 0.0 {
+            self.max_learnts = (self.clauses.len() as f64 / 3.0).max(2000.0);
+        }
+        if self.var_inc == 0.0 {
+            self.var_inc = 1.0;
+            self.clause_inc = 1.0;
+        }
+
+        let budget = Budget {
+            deadline: self.limits.time.map(|time| Instant::now() + time),
+            conflicts: self.limits.conflicts.map(|c| self.conflicts + c),
+            decisions: self.limits.decisions.map(|d| self.decisions + d),
+            terminate: self.limits.terminate.clone(),
+        };
+
+        let mut restart = 0;
+        let result = loop {
+            let conflict_budget = luby(restart) * RESTART_BASE;
+            match self.search(conflict_budget, &budget) {
+                Search::Sat => break true,
+                Search::Unsat => break false,
+                Search::Stop(result) => {
+                    self.assumptions.clear();
+                    return result;
+                }
+                Search::Restart => {
+                    restart += 1;
+                    self.restarts += 1;
+                    self.max_learnts *= 1.05;
+                }
+            }
+        };
+
+        if result {
+            self.model = self.assigns.iter().map(|&v| v == Value::True).collect();
+        } else if self.failed.is_empty() {
+            self.inconsistent = self.decision_level() == 0;
+        }
+
+        self.cancel_until(0);
+        self.assumptions.clear();
+
+        if result {
+            SolveResult::Sat
+        } else {
+            SolveResult::Unsat(Some(std::mem::take(&mut self.failed)))
+        }
+    }
+}
+
+/// Luby restart sequence (1, 1, 2, 1, 1, 2, 4, ...).
+fn luby(mut i: u64) -> u64 {
+    let mut size = 1;
+    let mut seq = 0;
+
+    while size < i + 1 {
+        seq += 1;
+        size = 2 * size + 1;
+    }
+
+    while size - 1 != i {
+        size = (size - 1) >> 1;
+        seq -= 1;
+        i %= size;
+    }
+
+    1 << seq
+}
+
+impl Backend for Cdcl {
+    fn add_clause<I>(&mut self, lits: I)
+    where
+        I: Iterator<Item = i32>,
+    {
+        if self.inconsistent {
+            return;
+        }
+
+        let mut clause: Vec<_> = lits.map(Lit::from_dimacs).collect();
+        if let Some(max) = clause.iter().map(|l| l.var() + 1).max() {
+            self.grow_to(max);
+        }
+
+        for lit in &clause {
+            if self.eliminated[lit.var()] {
+                self.eliminated[lit.var()] = false;
+                self.order.insert(lit.var(), &self.activity);
+            }
+        }
+
+        clause.sort_unstable_by_key(|l| l.0);
+        clause.dedup();
+
+        if clause.windows(2).any(|w| w[0] == !w[1])
+            || clause.iter().any(|&l| self.value_of(l) == Value::True)
+        {
+            return;
+        }
+        clause.retain(|&l| self.value_of(l) != Value::False);
+
+        match clause.len() {
+            0 => self.inconsistent = true,
+            1 => {
+                self.enqueue(clause[0], None);
+                if self.propagate().is_some() {
+                    self.inconsistent = true;
+                }
+            }
+            _ => {
+                self.push_clause(clause, false, 0);
+            }
+        }
+    }
+
+    fn add_debug_info<D: fmt::Debug>(&mut self, debug: D) {
+        println!("{:#?}", debug)
+    }
+
+    fn append_debug_info<D: fmt::Debug>(&mut self, debug: D) {
+        println!("{:?}", debug)
+    }
+}
+
+/// Returns the original clauses which aren't satisfied yet together with the
+/// fixed literals.
+impl ClauseDatabase for Cdcl {
+    fn clauses(&self) -> Vec<Vec<i32>> {
+        if self.inconsistent {
+            return vec![Vec::new()];
+        }
+
+        // Outside of solve calls all assignments are at decision level 0.
+        let units = self.trail.iter().map(|l| vec![l.to_dimacs()]);
+
+        let clauses = self
+            .clauses
+            .iter()
+            .filter(|c| !c.learnt && !c.deleted)
+            .filter(|c| c.lits.iter().all(|&l| self.value_of(l) != Value::True))
+            .map(|c| {
+                c.lits
+                    .iter()
+                    .filter(|&&l| self.value_of(l) == Value::Undef)
+                    .map(|l| l.to_dimacs())
+                    .collect()
+            });
+
+        units.chain(clauses).collect()
+    }
+
+    /// Uses the watches of the solver, but only the original clauses are
+    /// propagated. The literals fixed at decision level 0 are assigned already,
+    /// they may have been derived by earlier solve calls like the units returned
+    /// by [`clauses`](ClauseDatabase::clauses).
+    fn propagate_assumptions(
+        &mut self,
+        assumptions: &[i32],
+    ) -> Result<Vec<i32>, Vec<i32>> {
+        if self.inconsistent {
+            return Err(Vec::new());
+        }
+
+        self.skip_learnts = true;
+        let consistent = self.assume(assumptions);
+        self.skip_learnts = false;
+
+        let result = if consistent {
+            Ok(self.trail.iter().map(|l| l.to_dimacs()).collect())
+        } else {
+            Err(std::mem::take(&mut self.failed))
+        };
+
+        self.cancel_until(0);
+        result
+    }
+}
+
+impl Solver for Cdcl {
+    fn solve(&mut self) -> SolveResult {
+        match self.solve_internal(Vec::new()) {
+            SolveResult::Unsat(_) => SolveResult::Unsat(None),
+            result => result,
+        }
+    }
+
+    fn value(&mut self, var: i32) -> bool {
+        let value = self
+            .model
+            .get(var.unsigned_abs() as usize - 1)
+            .copied()
+            .unwrap_or(false);
+
+        if var < 0 {
+            !value
+        } else {
+            value
+        }
+    }
+
+    fn stats(&self) -> SolveStats {
+        self.stats
+    }
+
+    fn guarded_solve(&mut self, guards: &[i32]) -> SolveResult {
+        self.assumption_solve(guards.iter().copied())
+    }
+}
+
+impl IncrementalSolver for Cdcl {
+    fn assumption_solve<I>(&mut self, assumptions: I) -> SolveResult
+    where
+        I: Iterator<Item = i32>,
+    {
+        self.solve_internal(assumptions.collect())
+    }
+}
+
+impl LimitedSolver for Cdcl {
+    fn set_limits(&mut self, limits: SolveLimits) {
+        self.limits = limits;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constraints::ExactlyK, Lit as VarLit};
+
+    fn pigeon_hole(solver: &mut Cdcl, holes: i32) {
+        let pigeons = holes + 1;
+        let var = |p: i32, h: i32| p * holes + h + 1;
+
+        for p in 0..pigeons {
+            solver.add_clause((0..holes).map(|h| var(p, h)));
+        }
+
+        for h in 0..holes {
+            for p1 in 0..pigeons {
+                for p2 in (p1 + 1)..pigeons {
+                    solver.add_clause([-var(p1, h), -var(p2, h)].iter().copied());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn luby_sequence() {
+        let seq: Vec<_> = (0..15).map(luby).collect();
+        assert_eq!(seq, vec![1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+    }
+
+    #[test]
+    fn pigeon_hole_unsat() {
+        let mut solver = Cdcl::new();
+        pigeon_hole(&mut solver, 6);
+
+        assert_eq!(Solver::solve(&mut solver), SolveResult::Unsat(None));
+    }
+
+    #[test]
+    fn empty_clause_is_unsat() {
+        let mut solver = Cdcl::new();
+        solver.add_clause([1, 2].iter().copied());
+        solver.add_clause(std::iter::empty());
+
+        assert_eq!(Solver::solve(&mut solver), SolveResult::Unsat(None));
+    }
+
+    #[test]
+    fn model_satisfies_clauses() {
+        let mut solver = Cdcl::new();
+        let clauses = vec![vec![1, -2, 3], vec![-1, 2], vec![-3, -1], vec![2, 3]];
+
+        for clause in &clauses {
+            solver.add_clause(clause.iter().copied());
+        }
+
+        assert_eq!(Solver::solve(&mut solver), SolveResult::Sat);
+        for clause in &clauses {
+            assert!(clause.iter().any(|&l| solver.value(l)));
+        }
+    }
+
+    #[test]
+    fn failed_assumptions() {
+        let mut solver = Cdcl::new();
+        solver.add_clause([-1, 2].iter().copied());
+        solver.add_clause([-2, 3].iter().copied());
+
+        let result = solver.assumption_solve([4, 1, -3].iter().copied());
+        let mut core = match result {
+            SolveResult::Unsat(Some(core)) => core,
+            r => panic!("unexpected result {:?}", r),
+        };
+        core.sort_unstable();
+        assert_eq!(core, vec![-3, 1]);
+
+        // Assumptions are dropped after each call.
+        assert_eq!(Solver::solve(&mut solver), SolveResult::Sat);
+        assert_eq!(
+            solver.assumption_solve([1].iter().copied()),
+            SolveResult::Sat
+        );
+        assert!(solver.value(3));
+    }
+
+    #[test]
+    fn propagation_ignores_learnt_clauses() {
+        let mut solver = Cdcl::new();
+        solver.add_clause([-1, 2].iter().copied());
+        solver.add_clause([-2, 3, 4].iter().copied());
+
+        let learnt = vec![Lit::from_dimacs(-1), Lit::from_dimacs(3)];
+        solver.push_clause(learnt, true, 2);
+
+        assert_eq!(solver.propagate_assumptions(&[1]), Ok(vec![1, 2]));
+        assert_eq!(
+            solver.propagate_assumptions(&[1, -4]),
+            Ok(vec![1, 2, -4, 3])
+        );
+        assert_eq!(
+            solver.propagate_assumptions(&[1, -3, -4]),
+            Err(vec![-4, -3, 1])
+        );
+    }
+
+    #[test]
+    fn simplify_deletes_satisfied_clauses() {
+        let mut solver = Cdcl::new();
+        solver.add_clause([1, 2].iter().copied());
+        solver.add_clause([3, 4].iter().copied());
+        solver.add_clause([-2, 5, 6].iter().copied());
+        solver.add_clause([1].iter().copied());
+
+        assert_eq!(Solver::solve(&mut solver), SolveResult::Sat);
+
+        let live: Vec<_> = solver.clauses.iter().filter(|c| !c.deleted).collect();
+        assert_eq!(live.len(), 2);
+        assert!(solver.eliminated.iter().all(|&e| !e));
+
+        // Once [-2, 5, 6] is satisfied as well, 2, 5 and 6 don't occur anymore.
+        solver.add_clause([5].iter().copied());
+        assert_eq!(Solver::solve(&mut solver), SolveResult::Sat);
+        assert_eq!(
+            solver.eliminated,
+            vec![false, true, false, false, false, true]
+        );
+        assert_eq!(solver.clauses.iter().filter(|c| !c.deleted).count(), 1);
+    }
+
+    #[test]
+    fn eliminated_variables_stay_usable() {
+        let mut solver = Cdcl::new();
+        solver.add_clause([1, 2].iter().copied());
+        solver.add_clause([1].iter().copied());
+        assert_eq!(Solver::solve(&mut solver), SolveResult::Sat);
+        assert!(solver.eliminated[1]);
+
+        // Assumptions on eliminated variables are still respected.
+        assert_eq!(
+            solver.assumption_solve([-2].iter().copied()),
+            SolveResult::Sat
+        );
+        assert!(solver.value(-2));
+
+        // A new clause brings the variable back.
+        solver.add_clause([2, -1].iter().copied());
+        assert!(!solver.eliminated[1]);
+        assert_eq!(Solver::solve(&mut solver), SolveResult::Sat);
+        assert!(solver.value(2));
+
+        solver.add_clause([-2].iter().copied());
+        assert_eq!(Solver::solve(&mut solver), SolveResult::Unsat(None));
+    }
+
+    #[test]
+    fn encoder_enumerates_models() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(ExactlyK { k: 2, lits: 0..5 });
+
+        let mut count = 0;
+        while let Some(model) = encoder.solve() {
+            assert_eq!(model.vars().filter(|l| l.is_pos()).count(), 2);
+
+            let varmap = &encoder.varmap;
+            let blocking: Vec<_> = model
+                .vars()
+                .map(|l: VarLit<u32>| varmap.get_var(!l).unwrap())
+                .collect();
+            encoder.backend.add_clause(blocking.into_iter());
+            count += 1;
+        }
+        assert_eq!(count, 10);
+    }
+
+    #[test]
+    fn limits_stop_search() {
+        let mut solver = Cdcl::new();
+        pigeon_hole(&mut solver, 6);
+
+        solver.set_limits(SolveLimits::new().conflicts(10));
+        assert_eq!(Solver::solve(&mut solver), SolveResult::Unknown);
+
+        solver.set_limits(SolveLimits::new().decisions(10));
+        assert_eq!(Solver::solve(&mut solver), SolveResult::Unknown);
+        assert_eq!(solver.stats().decisions, 10);
+
+        solver.set_limits(SolveLimits::new().time(std::time::Duration::ZERO));
+        assert_eq!(Solver::solve(&mut solver), SolveResult::Interrupted);
+
+        let flag = Arc::new(AtomicBool::new(true));
+        solver.set_limits(SolveLimits::new().terminate(flag));
+        assert_eq!(Solver::solve(&mut solver), SolveResult::Interrupted);
+
+        // The solver stays usable after an interrupted call.
+        solver.set_limits(SolveLimits::new());
+        assert_eq!(Solver::solve(&mut solver), SolveResult::Unsat(None));
+    }
+}
@@ -1,6 +1,9 @@
-use std::fmt;
+use std::{
+    fmt,
+    io::{self, BufRead},
+};
 
-use crate::Backend;
+use crate::{Backend, ClauseDatabase, Encoder, SatVar, VarMap, VarType};
 
 enum DimacsEntry {
     Clause(Vec<i32>),
@@ -21,14 +24,25 @@ impl DimacsWriter {
     }
 
     /// Write collected clauses to `writer`.
-    pub fn write_to(&self, mut writer: impl std::io::Write) -> std::io::Result<()> {
+    pub fn write_to(&self, writer: impl std::io::Write) -> std::io::Result<()> {
+        self.write_with_units(writer, &[])
+    }
+
+    /// Write collected clauses followed by the unit clauses `units` to `writer`.
+    pub(crate) fn write_with_units(
+        &self,
+        mut writer: impl std::io::Write,
+        units: &[i32],
+    ) -> std::io::Result<()> {
         let clause_count = self
             .data
             .iter()
             .filter(|e| matches!(e, DimacsEntry::Clause(..)))
             .count();
 
-        writeln!(writer, "p cnf {} {}", self.max_var, clause_count)?;
+        let max_var = units.iter().map(|l| l.abs()).fold(self.max_var, i32::max);
+
+        writeln!(writer, "p cnf {} {}", max_var, clause_count + units.len())?;
 
         for entry in &self.data {
             match entry {
@@ -37,7 +51,7 @@ impl DimacsWriter {
                         write!(writer, "{} ", l)?
                     }
                     writeln!(writer, "0")?;
-                },
+                }
                 DimacsEntry::Comment(s) => {
                     for line in s.lines() {
                         writeln!(writer, "c {}", line)?;
@@ -46,6 +60,10 @@ impl DimacsWriter {
             }
         }
 
+        for unit in units {
+            writeln!(writer, "{} 0", unit)?;
+        }
+
         Ok(())
     }
 }
@@ -75,3 +93,459 @@ impl Backend for DimacsWriter {
         }
     }
 }
+
+impl ClauseDatabase for DimacsWriter {
+    fn clauses(&self) -> Vec<Vec<i32>> {
+        self.data
+            .iter()
+            .filter_map(|e| match e {
+                DimacsEntry::Clause(clause) => Some(clause.clone()),
+                DimacsEntry::Comment(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// Variable range and clause count of a formula read by [`read_dimacs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DimacsHeader {
+    /// Number of variables declared in the `p cnf` line.
+    pub vars: u32,
+    /// Number of clauses declared in the `p cnf` line.
+    pub clauses: usize,
+    offset: i32,
+}
+
+impl DimacsHeader {
+    /// Translate a literal of the DIMACS file to the (unnamed) variable it was
+    /// assigned in the [`VarMap`].
+    ///
+    /// Panics if `lit` is 0 or outside of the declared variable range.
+    pub fn lit<V>(&self, lit: i32) -> VarType<V> {
+        assert!(
+            lit != 0 && lit.unsigned_abs() <= self.vars,
+            "literal {} is outside of the DIMACS variable range",
+            lit
+        );
+
+        let var = self.offset + lit.abs() - 1;
+        VarType::Unnamed(if lit < 0 { -var } else { var })
+    }
+}
+
+/// Reason why a DIMACS file couldn't be read.
+#[derive(Debug)]
+pub enum DimacsErrorKind {
+    Io(io::Error),
+    MissingHeader,
+    DuplicateHeader,
+    InvalidHeader,
+    TooManyVars(u32),
+    InvalidLiteral(String),
+    VarOutOfRange(i32),
+    TooManyClauses(usize),
+    TooFewClauses { expected: usize, found: usize },
+    UnterminatedClause,
+}
+
+/// Error returned by [`read_dimacs`] with the 1-based position it occurred at.
+#[derive(Debug)]
+pub struct DimacsError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: DimacsErrorKind,
+}
+
+impl fmt::Display for DimacsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: ", self.line, self.column)?;
+
+        match &self.kind {
+            DimacsErrorKind::Io(e) => write!(f, "{}", e),
+            DimacsErrorKind::MissingHeader => {
+                write!(f, "expected `p cnf <vars> <clauses>` before first clause")
+            }
+            DimacsErrorKind::DuplicateHeader => write!(f, "duplicate `p` line"),
+            DimacsErrorKind::InvalidHeader => {
+                write!(f, "invalid header, expected `p cnf <vars> <clauses>`")
+            }
+            DimacsErrorKind::TooManyVars(vars) => {
+                write!(f, "{} variables exceed the free variable range", vars)
+            }
+            DimacsErrorKind::InvalidLiteral(token) => {
+                write!(f, "invalid literal `{}`", token)
+            }
+            DimacsErrorKind::VarOutOfRange(lit) => {
+                write!(f, "literal {} exceeds the declared variable count", lit)
+            }
+            DimacsErrorKind::TooManyClauses(expected) => {
+                write!(f, "more than the declared {} clauses", expected)
+            }
+            DimacsErrorKind::TooFewClauses { expected, found } => {
+                write!(f, "expected {} clauses, but found only {}", expected, found)
+            }
+            DimacsErrorKind::UnterminatedClause => {
+                write!(f, "last clause is not terminated by 0")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DimacsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            DimacsErrorKind::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Read a formula in the [DIMACS](https://www.cs.utexas.edu/users/moore/acl2/manuals/current/manual/index-seo.php/SATLINK____DIMACS) CNF format and add its clauses to `backend`.
+///
+/// The variables of the file are mapped to a freshly reserved range of unnamed
+/// variables in `varmap`, so they never collide with variables which are already in
+/// use.
+/// Use [`DimacsHeader::lit`] to refer to them in further constraints.
+/// No clause is added if the file contains an error.
+pub fn read_dimacs<V, B, R>(
+    reader: R,
+    backend: &mut B,
+    varmap: &mut VarMap<V>,
+) -> Result<DimacsHeader, DimacsError>
+where
+    B: Backend,
+    R: BufRead,
+{
+    let mut header: Option<(u32, usize)> = None;
+    let mut clauses: Vec<Vec<i32>> = Vec::new();
+    let mut clause = Vec::new();
+    let mut position = (0, 1);
+    // Start of the current clause and end of the last token or header.
+    let mut clause_start = (0, 1);
+    let mut end = (0, 1);
+
+    let error = |(line, column), kind| DimacsError { line, column, kind };
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| error((i + 1, 1), DimacsErrorKind::Io(e)))?;
+        let trimmed = line.trim_start();
+        position = (i + 1, line.chars().count() + 1);
+
+        if trimmed.is_empty() || trimmed.starts_with('c') {
+            continue;
+        }
+
+        // Some benchmark collections terminate the formula with a `%` line.
+        if trimmed.starts_with('%') {
+            break;
+        }
+
+        let indent = line.chars().count() - trimmed.chars().count();
+
+        if trimmed.starts_with('p') {
+            if header.is_some() {
+                return Err(error(
+                    (i + 1, indent + 1),
+                    DimacsErrorKind::DuplicateHeader,
+                ));
+            }
+
+            let fields: Vec<_> = trimmed.split_whitespace().collect();
+            header = match fields.as_slice() {
+                ["p", "cnf", vars, clauses] => vars
+                    .parse()
+                    .ok()
+                    .filter(|&v: &u32| v <= i32::MAX as u32)
+                    .zip(clauses.parse().ok()),
+                _ => None,
+            };
+
+            match header {
+                None => {
+                    return Err(error(
+                        (i + 1, indent + 1),
+                        DimacsErrorKind::InvalidHeader,
+                    ))
+                }
+                Some((vars, _)) if vars > varmap.free_vars() => {
+                    return Err(error(
+                        (i + 1, indent + 1),
+                        DimacsErrorKind::TooManyVars(vars),
+                    ))
+                }
+                _ => {}
+            }
+
+            end = (i + 1, indent + trimmed.trim_end().chars().count() + 1);
+            continue;
+        }
+
+        for (column, token) in tokens(&line) {
+            let position = (i + 1, column);
+
+            let (vars, expected) = match header {
+                Some(h) => h,
+                None => return Err(error(position, DimacsErrorKind::MissingHeader)),
+            };
+
+            let lit: i32 = token.parse().map_err(|_| {
+                error(position, DimacsErrorKind::InvalidLiteral(token.to_owned()))
+            })?;
+
+            if clause.is_empty() && clauses.len() == expected {
+                return Err(error(
+                    position,
+                    DimacsErrorKind::TooManyClauses(expected),
+                ));
+            }
+
+            if clause.is_empty() {
+                clause_start = position;
+            }
+            end = (i + 1, column + token.chars().count());
+
+            if lit == 0 {
+                clauses.push(std::mem::take(&mut clause));
+            } else if lit.unsigned_abs() > vars {
+                return Err(error(position, DimacsErrorKind::VarOutOfRange(lit)));
+            } else {
+                clause.push(lit);
+            }
+        }
+    }
+
+    let (vars, expected) = match header {
+        Some(h) => h,
+        None => return Err(error(position, DimacsErrorKind::MissingHeader)),
+    };
+
+    if !clause.is_empty() {
+        return Err(error(clause_start, DimacsErrorKind::UnterminatedClause));
+    }
+
+    if clauses.len() != expected {
+        return Err(error(
+            end,
+            DimacsErrorKind::TooFewClauses {
+                expected,
+                found: clauses.len(),
+            },
+        ));
+    }
+
+    let offset = varmap.new_vars(vars);
+
+    for clause in clauses {
+        backend.add_clause(clause.into_iter().map(|l| {
+            let var = offset + l.abs() - 1;
+            if l < 0 {
+                -var
+            } else {
+                var
+            }
+        }));
+    }
+
+    Ok(DimacsHeader {
+        vars,
+        clauses: expected,
+        offset,
+    })
+}
+
+/// Splits `line` into whitespace separated tokens with their 1-based column.
+fn tokens(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    line.split(char::is_whitespace)
+        .scan(1, |column, token| {
+            let start = *column;
+            *column += token.chars().count() + 1;
+            Some((start, token))
+        })
+        .filter(|(_, token)| !token.is_empty())
+}
+
+impl<V: SatVar, B: Backend> Encoder<V, B> {
+    /// Read a DIMACS CNF formula and add its clauses to this encoder.
+    /// See [`read_dimacs`] for details.
+    ///
+    /// Inside of a scope the clauses are removed again by [`pop`](Encoder::pop).
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::Or, Lit};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// let cnf = "c a small formula\np cnf 2 2\n1 -2 0\n2 0\n";
+    /// let header = encoder.read_dimacs(cnf.as_bytes()).unwrap();
+    ///
+    /// // Variable 1 of the file has to match "a".
+    /// encoder.add_constraint(Or(vec![header.lit(-1), Lit::Pos("a").into()].into_iter()));
+    /// encoder.add_constraint(Or(vec![header.lit(1), Lit::Neg("a").into()].into_iter()));
+    ///
+    /// let model = encoder.solve().unwrap();
+    /// assert!(model["a"]);
+    /// # }
+    /// ```
+    pub fn read_dimacs<R: BufRead>(
+        &mut self,
+        reader: R,
+    ) -> Result<DimacsHeader, DimacsError> {
+        let (mut backend, varmap) = self.scoped();
+        read_dimacs(reader, &mut backend, varmap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{mock::MockSolver, CdclEncoder};
+
+    fn read(input: &str) -> Result<Vec<Vec<i32>>, DimacsError> {
+        let mut backend = MockSolver::default();
+        let mut varmap = VarMap::<u32>::default();
+        read_dimacs(input.as_bytes(), &mut backend, &mut varmap)?;
+        Ok(backend.get_clauses())
+    }
+
+    fn read_err(input: &str) -> (usize, usize, DimacsErrorKind) {
+        let e = read(input).unwrap_err();
+        (e.line, e.column, e.kind)
+    }
+
+    #[test]
+    fn read_simple() {
+        let input = "c comment\np cnf 3 2\n1 -3 0\nc another\n  2 3 -1 0\n";
+        assert_eq!(read(input).unwrap(), vec![vec![1, -3], vec![2, 3, -1]]);
+    }
+
+    #[test]
+    fn read_multiline_clauses() {
+        let input = "p cnf 4 3\n1 2\n 3 0 -4\n0 1 0\n%\n0\n";
+        assert_eq!(read(input).unwrap(), vec![vec![1, 2, 3], vec![-4], vec![1]]);
+    }
+
+    #[test]
+    fn read_offset_variables() {
+        let mut backend = MockSolver::default();
+        let mut varmap = VarMap::<u32>::default();
+        varmap.add_var(7);
+        varmap.add_var(8);
+
+        let header =
+            read_dimacs("p cnf 2 1\n-1 2 0".as_bytes(), &mut backend, &mut varmap)
+                .unwrap();
+
+        assert_eq!(backend.get_clauses(), vec![vec![-3, 4]]);
+        assert_eq!(header.lit::<u32>(-2), VarType::Unnamed(-4));
+        assert_eq!(varmap.new_var(), 5);
+    }
+
+    #[test]
+    fn write_read_roundtrip() {
+        let mut writer = DimacsWriter::new();
+        writer.add_debug_info("some constraint\nover two lines");
+        writer.add_clause([1, -2, 3].iter().copied());
+        writer.add_clause([-3].iter().copied());
+
+        let mut buffer = Vec::new();
+        writer.write_to(&mut buffer).unwrap();
+
+        let input = String::from_utf8(buffer).unwrap();
+        assert_eq!(read(&input).unwrap(), vec![vec![1, -2, 3], vec![-3]]);
+    }
+
+    #[test]
+    fn read_in_scope() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.push();
+        let header = encoder.read_dimacs("p cnf 1 1\n-1 0\n".as_bytes()).unwrap();
+        encoder.add_constraint(header.lit::<u32>(1));
+        assert!(encoder.solve().is_none());
+        encoder.pop();
+
+        encoder.add_constraint(header.lit::<u32>(1));
+        assert!(encoder.solve().is_some());
+    }
+
+    #[test]
+    fn read_too_many_vars() {
+        let mut backend = MockSolver::default();
+        let mut varmap = VarMap::<u32>::default();
+        varmap.add_var(7);
+
+        let input = format!("p cnf {} 0\n", i32::MAX);
+        let e =
+            read_dimacs(input.as_bytes(), &mut backend, &mut varmap).unwrap_err();
+        assert!(
+            matches!(e.kind, DimacsErrorKind::TooManyVars(v) if v == i32::MAX as u32)
+        );
+        assert_eq!(varmap.new_var(), 2);
+
+        let input = format!("p cnf {} 0\n", i32::MAX - 3);
+        let header =
+            read_dimacs(input.as_bytes(), &mut backend, &mut varmap).unwrap();
+        assert_eq!(header.lit::<u32>(-1), VarType::Unnamed(-3));
+        assert_eq!(varmap.free_vars(), 0);
+    }
+
+    #[test]
+    fn read_errors() {
+        assert!(matches!(
+            read_err("c nothing\n1 2 0\n"),
+            (2, 1, DimacsErrorKind::MissingHeader)
+        ));
+        assert!(matches!(
+            read_err("p cnf 2 1\n p cnf 2 1\n"),
+            (2, 2, DimacsErrorKind::DuplicateHeader)
+        ));
+        assert!(matches!(
+            read_err("p cnf x 1\n"),
+            (1, 1, DimacsErrorKind::InvalidHeader)
+        ));
+        assert!(matches!(
+            read_err("p cnf 2 1\n1  2x 0\n"),
+            (2, 4, DimacsErrorKind::InvalidLiteral(t)) if t == "2x"
+        ));
+        assert!(matches!(
+            read_err("p cnf 2 1\n1 -3 0\n"),
+            (2, 3, DimacsErrorKind::VarOutOfRange(-3))
+        ));
+        assert!(matches!(
+            read_err("p cnf 2 1\n1 0 2 0\n"),
+            (2, 5, DimacsErrorKind::TooManyClauses(1))
+        ));
+        assert!(matches!(
+            read_err("p cnf 2 2\n1 0\n"),
+            (
+                2,
+                4,
+                DimacsErrorKind::TooFewClauses {
+                    expected: 2,
+                    found: 1
+                }
+            )
+        ));
+        assert!(matches!(
+            read_err("p cnf 2 2\n1 0\nc trailing comment\n"),
+            (2, 4, DimacsErrorKind::TooFewClauses { .. })
+        ));
+        assert!(matches!(
+            read_err("p cnf 2 1\nc no clauses\n"),
+            (1, 10, DimacsErrorKind::TooFewClauses { .. })
+        ));
+        assert!(matches!(
+            read_err("p cnf 2 2\n1 0\n 1\n2\nc trailing comment\n"),
+            (3, 2, DimacsErrorKind::UnterminatedClause)
+        ));
+        // Columns count characters, not bytes.
+        assert!(matches!(
+            read_err("p cnf 2 1\n1\u{3000}x 0\n"),
+            (2, 3, DimacsErrorKind::InvalidLiteral(t)) if t == "x"
+        ));
+        assert!(matches!(
+            read_err("p cnf 2 1\n\u{3000}p cnf 2 1\n"),
+            (2, 2, DimacsErrorKind::DuplicateHeader)
+        ));
+    }
+}
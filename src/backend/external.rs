@@ -0,0 +1,667 @@
+use std::{
+    ffi::{OsStr, OsString},
+    fmt, fs,
+    io::{self, Read, Write},
+    path::PathBuf,
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::DimacsWriter;
+use crate::{
+    Backend, ClauseDatabase, IncrementalSolver, LimitedSolver, SolveLimits,
+    SolveResult, SolveStats, Solver,
+};
+
+/// How the formula is handed to the external solver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExternalInput {
+    /// Write the formula to the standard input of the solver.
+    Stdin,
+    /// Write the formula to a temporary file whose path is passed as last argument.
+    TempFile,
+}
+
+/// Error which occurred during the last call to an [`ExternalSolver`].
+#[derive(Debug)]
+pub enum ExternalSolverError {
+    /// The solver process couldn't be started.
+    Spawn(io::Error),
+    /// Communicating with the solver process failed.
+    Io(io::Error),
+    /// A line of the solver output couldn't be parsed.
+    MalformedOutput { line: usize, content: String },
+    /// The solver exited without reporting a result.
+    UnexpectedExit(ExitStatus),
+    /// The exit code contradicts the reported `s` line.
+    Inconsistent { status: ExitStatus, result: String },
+}
+
+impl fmt::Display for ExternalSolverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExternalSolverError::Spawn(e) => {
+                write!(f, "failed to start solver: {}", e)
+            }
+            ExternalSolverError::Io(e) => write!(f, "solver io error: {}", e),
+            ExternalSolverError::MalformedOutput { line, content } => {
+                write!(f, "malformed solver output in line {}: `{}`", line, content)
+            }
+            ExternalSolverError::UnexpectedExit(status) => {
+                write!(f, "solver exited without result ({})", status)
+            }
+            ExternalSolverError::Inconsistent { status, result } => {
+                write!(f, "solver reported `{}` but exited with {}", result, status)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExternalSolverError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExternalSolverError::Spawn(e) | ExternalSolverError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Backend which runs an external SAT solver binary, like
+/// [kissat](https://github.com/arminbiere/kissat), on every call to `solve`.
+///
+/// Clauses are buffered like in [`DimacsWriter`] and passed to the solver in DIMACS
+/// format.
+/// The output has to follow the
+/// [SAT competition](http://www.satcompetition.org/2009/format-solvers2009.html)
+/// conventions: a `s SATISFIABLE`, `s UNSATISFIABLE` or `s UNKNOWN` line,
+/// `v` lines with the model and optionally the exit codes 10 and 20.
+///
+/// If the solver exceeds its timeout it is killed and `solve` returns
+/// [`SolveResult::Interrupted`].
+/// Of the [`SolveLimits`] only the time limit and the terminate flag are supported,
+/// conflict and decision budgets are ignored.
+/// Any other failure results in [`SolveResult::Unknown`], the reason is available
+/// using [`last_error`](ExternalSolver::last_error).
+///
+/// Only the wall-clock time of the solver process is reported as statistics.
+///
+/// Assumptions are passed as additional unit clauses.
+/// Since the solver doesn't report which of them failed, all assumptions are returned
+/// as core.
+///
+/// # Example
+/// ```rust,no_run
+/// # use std::time::Duration;
+/// # use satoxid::{backend::ExternalSolver, constraints::ExactlyK, Encoder};
+/// # fn main() {
+/// let solver = ExternalSolver::new("kissat")
+///     .arg("--quiet")
+///     .timeout(Duration::from_secs(60));
+/// let mut encoder = Encoder::with_backend(solver);
+///
+/// encoder.add_constraint(ExactlyK {
+///     k: 1,
+///     lits: ["a", "b", "c"].iter().copied(),
+/// });
+///
+/// let model = encoder.solve().unwrap();
+/// # }
+/// ```
+pub struct ExternalSolver {
+    program: OsString,
+    args: Vec<OsString>,
+    input: ExternalInput,
+    timeout: Option<Duration>,
+    limits: SolveLimits,
+    stats: SolveStats,
+    formula: DimacsWriter,
+    model: Vec<bool>,
+    error: Option<ExternalSolverError>,
+}
+
+impl ExternalSolver {
+    /// Create a new backend which runs `program`.
+    pub fn new(program: impl AsRef<OsStr>) -> Self {
+        Self {
+            program: program.as_ref().to_owned(),
+            args: Vec::new(),
+            input: ExternalInput::Stdin,
+            timeout: None,
+            limits: SolveLimits::default(),
+            stats: SolveStats::default(),
+            formula: DimacsWriter::new(),
+            model: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Add an argument which is passed to the solver.
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    /// Add multiple arguments which are passed to the solver.
+    pub fn args<I>(mut self, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<OsStr>,
+    {
+        self.args
+            .extend(args.into_iter().map(|a| a.as_ref().to_owned()));
+        self
+    }
+
+    /// Set how the formula is passed to the solver. Defaults to
+    /// [`ExternalInput::Stdin`].
+    pub fn input(mut self, input: ExternalInput) -> Self {
+        self.input = input;
+        self
+    }
+
+    /// Kill the solver if it runs longer than `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the error of the last `solve` call, if any.
+    pub fn last_error(&self) -> Option<&ExternalSolverError> {
+        self.error.as_ref()
+    }
+
+    fn run(&mut self, units: &[i32]) -> SolveResult {
+        self.model.clear();
+        self.error = None;
+
+        let start = Instant::now();
+        let result = self.run_process(units);
+        self.stats = SolveStats {
+            time: start.elapsed(),
+            ..SolveStats::default()
+        };
+
+        match result {
+            Ok(result) => result,
+            Err(e) => {
+                self.error = Some(e);
+                SolveResult::Unknown
+            }
+        }
+    }
+
+    fn run_process(
+        &mut self,
+        units: &[i32],
+    ) -> Result<SolveResult, ExternalSolverError> {
+        let mut formula = Vec::new();
+        self.formula
+            .write_with_units(&mut formula, units)
+            .map_err(ExternalSolverError::Io)?;
+
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+
+        let file = match self.input {
+            ExternalInput::Stdin => {
+                command.stdin(Stdio::piped());
+                None
+            }
+            ExternalInput::TempFile => {
+                let file =
+                    TempFile::new(&formula).map_err(ExternalSolverError::Io)?;
+                command.stdin(Stdio::null()).arg(&file.0);
+                Some(file)
+            }
+        };
+
+        // Run the solver in its own process group, so processes started by a
+        // wrapper script are killed together with it.
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+        let mut child = command.spawn().map_err(ExternalSolverError::Spawn)?;
+
+        let writer = child.stdin.take().map(|mut stdin| {
+            thread::spawn(move || {
+                // The solver may exit without reading its whole input.
+                let _ = stdin.write_all(&formula);
+            })
+        });
+
+        let mut stdout = child.stdout.take().unwrap();
+        let reader = thread::spawn(move || {
+            let mut output = String::new();
+            stdout.read_to_string(&mut output).map(|_| output)
+        });
+
+        let status = self.wait(&mut child);
+        if status.is_err() {
+            let _ = kill(&mut child);
+            let _ = child.wait();
+        }
+
+        // Once the solver is gone its pipes are closed, so both threads finish.
+        if let Some(writer) = writer {
+            let _ = writer.join();
+        }
+        let output = reader.join().expect("solver output reader panicked");
+        drop(file);
+
+        let status = match status.map_err(ExternalSolverError::Io)? {
+            Some(status) => status,
+            None => return Ok(SolveResult::Interrupted),
+        };
+        let output = output.map_err(ExternalSolverError::Io)?;
+
+        self.parse_output(&output, status, units)
+    }
+
+    /// Waits for `child` to exit. Returns `None` if it was killed due to a timeout
+    /// or termination request.
+    fn wait(&self, child: &mut Child) -> io::Result<Option<ExitStatus>> {
+        let timeout = match (self.timeout, self.limits.time) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let terminate = self.limits.terminate.as_ref();
+
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(Some(status));
+            }
+
+            if deadline.is_some_and(|d| Instant::now() >= d)
+                || terminate.is_some_and(|flag| flag.load(Ordering::Relaxed))
+            {
+                kill(child)?;
+                child.wait()?;
+                return Ok(None);
+            }
+
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn parse_output(
+        &mut self,
+        output: &str,
+        status: ExitStatus,
+        units: &[i32],
+    ) -> Result<SolveResult, ExternalSolverError> {
+        let mut result = None;
+        let mut values = Vec::new();
+
+        for (i, line) in output.lines().enumerate() {
+            let malformed = || ExternalSolverError::MalformedOutput {
+                line: i + 1,
+                content: line.to_owned(),
+            };
+
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("s") => {
+                    let r = fields.collect::<Vec<_>>().join(" ");
+                    if !matches!(
+                        r.as_str(),
+                        "SATISFIABLE" | "UNSATISFIABLE" | "UNKNOWN"
+                    ) || result.is_some()
+                    {
+                        return Err(malformed());
+                    }
+                    result = Some(r);
+                }
+                Some("v") => {
+                    for lit in fields {
+                        let lit: i32 = lit.parse().map_err(|_| malformed())?;
+                        if lit != 0 {
+                            values.push(lit);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let unsat = || SolveResult::Unsat(Some(units.to_vec()));
+
+        let result = match (result.as_deref(), status.code()) {
+            (Some("SATISFIABLE"), Some(0 | 10)) => SolveResult::Sat,
+            (Some("UNSATISFIABLE"), Some(0 | 20)) | (None, Some(20)) => unsat(),
+            (Some("UNKNOWN"), _) => SolveResult::Unknown,
+            (None, _) => return Err(ExternalSolverError::UnexpectedExit(status)),
+            (Some(r), _) => {
+                return Err(ExternalSolverError::Inconsistent {
+                    status,
+                    result: r.to_owned(),
+                })
+            }
+        };
+
+        if result == SolveResult::Sat {
+            for lit in values {
+                let var = lit.unsigned_abs() as usize;
+                if self.model.len() < var {
+                    self.model.resize(var, false);
+                }
+                self.model[var - 1] = lit > 0;
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Kills the process group of the solver, which `run_process` creates.
+#[cfg(unix)]
+fn kill(child: &mut Child) -> io::Result<()> {
+    let group = child.id() as libc::pid_t;
+
+    // SAFETY: `kill` only sends a signal and doesn't access any memory.
+    if unsafe { libc::kill(-group, libc::SIGKILL) } == 0 {
+        return Ok(());
+    }
+
+    match io::Error::last_os_error() {
+        // All processes of the group already exited.
+        e if e.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+        e => Err(e),
+    }
+}
+
+#[cfg(not(unix))]
+fn kill(child: &mut Child) -> io::Result<()> {
+    child.kill()
+}
+
+/// Temporary file which is removed on drop.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    /// Creates a new file with `contents` in the temporary directory.
+    ///
+    /// The file must not exist yet, so an existing file or a symlink planted at
+    /// the path isn't written to.
+    fn new(contents: &[u8]) -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        loop {
+            let name = format!(
+                "satoxid-{}-{}.cnf",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            );
+            let path = std::env::temp_dir().join(name);
+
+            let mut file = match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            };
+
+            // The file is removed again if writing fails.
+            let temp = Self(path);
+            file.write_all(contents)?;
+            return Ok(temp);
+        }
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+impl Backend for ExternalSolver {
+    fn add_clause<I>(&mut self, lits: I)
+    where
+        I: Iterator<Item = i32>,
+    {
+        self.formula.add_clause(lits);
+    }
+
+    fn add_debug_info<D: fmt::Debug>(&mut self, debug: D) {
+        self.formula.add_debug_info(debug);
+    }
+
+    fn append_debug_info<D: fmt::Debug>(&mut self, debug: D) {
+        self.formula.append_debug_info(debug);
+    }
+}
+
+impl ClauseDatabase for ExternalSolver {
+    fn clauses(&self) -> Vec<Vec<i32>> {
+        self.formula.clauses()
+    }
+}
+
+impl Solver for ExternalSolver {
+    fn solve(&mut self) -> SolveResult {
+        match self.run(&[]) {
+            SolveResult::Unsat(_) => SolveResult::Unsat(None),
+            result => result,
+        }
+    }
+
+    fn value(&mut self, var: i32) -> bool {
+        let value = self
+            .model
+            .get(var.unsigned_abs() as usize - 1)
+            .copied()
+            .unwrap_or(false);
+
+        if var < 0 {
+            !value
+        } else {
+            value
+        }
+    }
+
+    fn stats(&self) -> SolveStats {
+        self.stats
+    }
+
+    fn guarded_solve(&mut self, guards: &[i32]) -> SolveResult {
+        self.assumption_solve(guards.iter().copied())
+    }
+}
+
+impl IncrementalSolver for ExternalSolver {
+    fn assumption_solve<I>(&mut self, assumptions: I) -> SolveResult
+    where
+        I: Iterator<Item = i32>,
+    {
+        let units: Vec<_> = assumptions.collect();
+        self.run(&units)
+    }
+}
+
+impl LimitedSolver for ExternalSolver {
+    fn set_limits(&mut self, limits: SolveLimits) {
+        self.limits = limits;
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::sync::{atomic::AtomicBool, Arc};
+
+    use super::*;
+    use crate::{constraints::ExactlyK, Encoder, Lit, SolveOutcome};
+
+    /// Shell script which acts as a solver. It is written to its own directory,
+    /// which is removed on drop.
+    struct StubSolver {
+        dir: PathBuf,
+        script: PathBuf,
+    }
+
+    impl StubSolver {
+        fn new(name: &str, script: &str) -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+            let dir = std::env::temp_dir().join(format!(
+                "satoxid-stub-{}-{}-{}",
+                std::process::id(),
+                name,
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            fs::create_dir(&dir).unwrap();
+
+            let path = dir.join("solver.sh");
+            let mut file = fs::File::create(&path).unwrap();
+            writeln!(file, "#!/bin/sh\n{}", script).unwrap();
+            drop(file);
+
+            Self { dir, script: path }
+        }
+
+        /// The script is run by `sh` instead of being executed itself. Otherwise
+        /// spawning fails with ETXTBSY if another test forks while the file is
+        /// still open for writing.
+        fn solver(&self) -> ExternalSolver {
+            ExternalSolver::new("sh").arg(&self.script)
+        }
+    }
+
+    impl Drop for StubSolver {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn satisfiable_output() {
+        let script = "cat > /dev/null\n\
+                      echo 'c some comment'\n\
+                      echo 's SATISFIABLE'\n\
+                      echo 'v -1 2'\n\
+                      echo 'v 3 0'\n\
+                      exit 10";
+        let stub = StubSolver::new("sat", script);
+        let mut encoder = Encoder::with_backend(stub.solver());
+
+        encoder.add_constraint(ExactlyK {
+            k: 2,
+            lits: ["a", "b", "c"].iter().copied(),
+        });
+
+        let model = encoder.solve().unwrap();
+        assert!(!model["a"]);
+        assert!(model["b"]);
+        assert!(model["c"]);
+    }
+
+    #[test]
+    fn unsatisfiable_exit_code() {
+        let script = "cat > /dev/null\nexit 20";
+        let stub = StubSolver::new("unsat", script);
+        let mut solver = stub.solver();
+        solver.add_clause([1].iter().copied());
+
+        assert_eq!(solver.solve(), SolveResult::Unsat(None));
+        assert_eq!(
+            solver.assumption_solve([-1, 2].iter().copied()),
+            SolveResult::Unsat(Some(vec![-1, 2]))
+        );
+    }
+
+    #[test]
+    fn formula_in_temp_file() {
+        let script =
+            "grep -q '^-2 0$' \"$1\" && echo 's UNSATISFIABLE' && exit 20\n\
+                      echo 's SATISFIABLE'\n\
+                      echo 'v 1 2 0'\n\
+                      exit 10";
+        let stub = StubSolver::new("file", script);
+        let mut solver = stub.solver().input(ExternalInput::TempFile);
+        solver.add_clause([1, 2].iter().copied());
+
+        assert_eq!(solver.solve(), SolveResult::Sat);
+        assert!(solver.value(1) && solver.value(2));
+        assert_eq!(
+            solver.assumption_solve([-2].iter().copied()),
+            SolveResult::Unsat(Some(vec![-2]))
+        );
+    }
+
+    #[test]
+    fn timeout_interrupts() {
+        // `sleep` is a child of the shell which keeps stdout open, so it has to be
+        // killed as well.
+        let stub = StubSolver::new("slow", "sleep 5");
+        let solver = stub.solver().timeout(Duration::from_millis(100));
+        let mut encoder = Encoder::<u32, _>::with_backend(solver);
+        encoder.add_constraint(Lit::Pos(1));
+
+        let start = Instant::now();
+        assert!(encoder.solve().is_none());
+        assert_eq!(encoder.backend.solve(), SolveResult::Interrupted);
+        assert!(start.elapsed() < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn terminate_flag_interrupts() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let stub = StubSolver::new("terminate", "sleep 5");
+        let mut encoder = Encoder::<u32, _>::with_backend(stub.solver());
+        encoder.add_constraint(Lit::Pos(1));
+
+        let setter = {
+            let flag = Arc::clone(&flag);
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(100));
+                flag.store(true, Ordering::Relaxed);
+            })
+        };
+
+        let start = Instant::now();
+        let limits = SolveLimits::new().terminate(flag);
+        assert!(matches!(
+            encoder.solve_with_limits(limits),
+            SolveOutcome::Interrupted
+        ));
+        assert!(start.elapsed() < Duration::from_secs(4));
+        setter.join().unwrap();
+    }
+
+    #[test]
+    fn malformed_output() {
+        let script =
+            "cat > /dev/null\necho 's SATISFIABLE'\necho 'v 1 x 0'\nexit 10";
+        let stub = StubSolver::new("malformed", script);
+        let mut solver = stub.solver();
+
+        assert_eq!(solver.solve(), SolveResult::Unknown);
+        assert!(matches!(
+            solver.last_error(),
+            Some(ExternalSolverError::MalformedOutput { line: 2, .. })
+        ));
+
+        let stub = StubSolver::new("no-result", "exit 3");
+        let mut solver = stub.solver();
+        assert_eq!(solver.solve(), SolveResult::Unknown);
+        assert!(matches!(
+            solver.last_error(),
+            Some(ExternalSolverError::UnexpectedExit(_))
+        ));
+
+        let mut solver = ExternalSolver::new("satoxid-nonexistent-solver");
+        assert_eq!(solver.solve(), SolveResult::Unknown);
+        assert!(matches!(
+            solver.last_error(),
+            Some(ExternalSolverError::Spawn(_))
+        ));
+    }
+}
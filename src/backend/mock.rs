@@ -1,4 +1,4 @@
-use crate::{Backend, IncrementalSolver, SolveResult, Solver};
+use crate::{Backend, ClauseDatabase, IncrementalSolver, SolveResult, Solver};
 
 #[derive(Default)]
 pub struct MockSolver {
@@ -8,9 +8,6 @@ impl MockSolver {
     pub fn get_clauses(&self) -> Vec<Vec<i32>> {
         self.clauses.clone()
     }
-    pub fn clear_clauses(&mut self) {
-        self.clauses.clear();
-    }
 }
 
 impl Backend for MockSolver {
@@ -23,18 +20,28 @@ impl Backend for MockSolver {
     }
 }
 
+impl ClauseDatabase for MockSolver {
+    fn clauses(&self) -> Vec<Vec<i32>> {
+        self.clauses.clone()
+    }
+}
+
 impl Solver for MockSolver {
     fn solve(&mut self) -> SolveResult {
         SolveResult::Unknown
     }
 
-    fn value(&mut self, var: i32) -> bool {
+    fn value(&mut self, _var: i32) -> bool {
         false
     }
+
+    fn guarded_solve(&mut self, guards: &[i32]) -> SolveResult {
+        self.assumption_solve(guards.iter().copied())
+    }
 }
 
 impl IncrementalSolver for MockSolver {
-    fn assumption_solve<I>(&mut self, assumptions: I) -> SolveResult
+    fn assumption_solve<I>(&mut self, _assumptions: I) -> SolveResult
     where
         I: Iterator<Item = i32>,
     {
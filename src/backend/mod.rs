@@ -1,8 +1,18 @@
+//! Backends which receive the encoded clauses.
+
+mod cdcl;
 mod dimacs;
-pub use dimacs::DimacsWriter;
+mod external;
+mod recorder;
+pub use cdcl::{Cdcl, CdclEncoder};
+pub use dimacs::{
+    read_dimacs, DimacsError, DimacsErrorKind, DimacsHeader, DimacsWriter,
+};
+pub use external::{ExternalInput, ExternalSolver, ExternalSolverError};
+pub use recorder::Recorder;
 
 #[cfg(feature = "cadical")]
 mod cadical;
 #[cfg(feature = "cadical")]
-pub use self::cadical::CadicalEncoder;
-pub mod mock;
+pub use self::cadical::{CadicalEncoder, LimitCallbacks, RecordingCadicalEncoder};
+pub(crate) mod mock;
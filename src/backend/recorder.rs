@@ -0,0 +1,121 @@
+use std::fmt;
+
+use crate::{
+    propagate::Propagator, Backend, ClauseDatabase, IncrementalSolver,
+    LimitedSolver, SolveLimits, SolveResult, SolveStats, Solver,
+};
+
+/// Backend adapter which keeps a copy of every clause passed to the wrapped solver.
+///
+/// This makes the clause database available for solvers which don't expose it,
+/// e.g. for [`Encoder::propagate`](crate::Encoder::propagate). The watch lists for
+/// unit propagation are kept between calls.
+///
+/// # Example
+/// ```rust
+/// # use satoxid::{backend::{Cdcl, Recorder}, constraints::AtMostK, Encoder, Lit};
+/// # fn main() {
+/// let mut encoder = Encoder::<_, Recorder<Cdcl>>::new();
+///
+/// encoder.add_constraint(AtMostK { k: 1, lits: ["a", "b"].iter().copied() });
+///
+/// let implied = encoder.propagate(&[Lit::Pos("a")]).unwrap();
+/// assert_eq!(implied, vec![Lit::Neg("b")]);
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Recorder<S> {
+    solver: S,
+    propagator: Propagator,
+}
+
+impl<S> Recorder<S> {
+    /// Wraps `solver`. Clauses which were added to it before aren't recorded.
+    pub fn new(solver: S) -> Self {
+        Self {
+            solver,
+            propagator: Propagator::default(),
+        }
+    }
+
+    /// The wrapped solver.
+    pub fn solver(&self) -> &S {
+        &self.solver
+    }
+
+    /// Mutable access to the wrapped solver.
+    /// Clauses which are added to it directly aren't recorded.
+    pub fn solver_mut(&mut self) -> &mut S {
+        &mut self.solver
+    }
+
+    /// Returns the wrapped solver and drops the recorded clauses.
+    pub fn into_inner(self) -> S {
+        self.solver
+    }
+}
+
+impl<S: Backend> Backend for Recorder<S> {
+    fn add_clause<I>(&mut self, lits: I)
+    where
+        I: Iterator<Item = i32>,
+    {
+        let clause: Vec<_> = lits.collect();
+        self.solver.add_clause(clause.iter().copied());
+        self.propagator.add_clause(clause);
+    }
+
+    fn add_debug_info<D: fmt::Debug>(&mut self, debug: D) {
+        self.solver.add_debug_info(debug);
+    }
+
+    fn append_debug_info<D: fmt::Debug>(&mut self, debug: D) {
+        self.solver.append_debug_info(debug);
+    }
+}
+
+impl<S: Backend> ClauseDatabase for Recorder<S> {
+    fn clauses(&self) -> Vec<Vec<i32>> {
+        self.propagator.clauses().to_vec()
+    }
+
+    fn propagate_assumptions(
+        &mut self,
+        assumptions: &[i32],
+    ) -> Result<Vec<i32>, Vec<i32>> {
+        self.propagator.propagate(assumptions)
+    }
+}
+
+impl<S: Solver> Solver for Recorder<S> {
+    fn solve(&mut self) -> SolveResult {
+        self.solver.solve()
+    }
+
+    fn value(&mut self, var: i32) -> bool {
+        self.solver.value(var)
+    }
+
+    fn stats(&self) -> SolveStats {
+        self.solver.stats()
+    }
+
+    fn guarded_solve(&mut self, guards: &[i32]) -> SolveResult {
+        self.solver.guarded_solve(guards)
+    }
+}
+
+impl<S: IncrementalSolver> IncrementalSolver for Recorder<S> {
+    fn assumption_solve<I>(&mut self, assumptions: I) -> SolveResult
+    where
+        I: Iterator<Item = i32>,
+    {
+        self.solver.assumption_solve(assumptions)
+    }
+}
+
+impl<S: LimitedSolver> LimitedSolver for Recorder<S> {
+    fn set_limits(&mut self, limits: SolveLimits) {
+        self.solver.set_limits(limits);
+    }
+}
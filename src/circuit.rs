@@ -8,7 +8,7 @@ pub enum Direction {
 }
 
 impl Direction {
-    fn in_to_out(&self) -> bool {
+    pub fn in_to_out(&self) -> bool {
         match self {
             Direction::Both => true,
             Direction::InToOut => true,
@@ -16,7 +16,7 @@ impl Direction {
         }
     }
 
-    fn out_to_in(&self) -> bool {
+    pub fn out_to_in(&self) -> bool {
         match self {
             Direction::Both => true,
             Direction::InToOut => false,
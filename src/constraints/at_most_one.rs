@@ -0,0 +1,485 @@
+use core::fmt;
+use std::fmt::Debug;
+
+use super::Encoded;
+use crate::{clause, Backend, Constraint, ConstraintRepr, SatVar, VarMap, VarType};
+
+/// Encoding of an [`AtMostOne`] constraint.
+///
+/// For `n` literals the pairwise encoding needs `n²/2` clauses and no auxiliary
+/// variables, the sequential encoding `3n` clauses and `n` variables. The
+/// commander, product and bimander encodings need about `2n` to `n log n` clauses,
+/// but only `n / 3`, `2√n` and `log n` auxiliary variables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AtMostOneEncoding {
+    Pairwise,
+    /// Sequential counter, also known as ladder encoding.
+    Sequential,
+    /// Commander encoding with groups of three literals.
+    Commander,
+    Product,
+    /// Bimander encoding with groups of two literals.
+    Bimander,
+}
+
+impl AtMostOneEncoding {
+    /// The encoding used for `n` literals if none is selected.
+    fn select(n: usize) -> Self {
+        match n {
+            0..=6 => AtMostOneEncoding::Pairwise,
+            7..=32 => AtMostOneEncoding::Sequential,
+            _ => AtMostOneEncoding::Product,
+        }
+    }
+}
+
+/// This constraint encodes the requirement that at most one of `lits` is true.
+///
+/// Unlike [`AtMostK`] with `k: 1` it uses a dedicated encoding, which is selected by
+/// the number of literals unless chosen with `with_encoding`.
+///
+/// # Example
+/// ```rust
+/// # use satoxid::{CadicalEncoder, constraints::{AtMostOne, AtMostOneEncoding}};
+/// # fn main() {
+/// # let mut encoder = CadicalEncoder::new();
+/// encoder.add_constraint(AtMostOne {
+///     lits: vec!["a", "b", "c", "d"].into_iter(),
+/// });
+///
+/// let constraint = AtMostOne {
+///     lits: vec!["c", "d", "e", "f"].into_iter(),
+/// }
+/// .with_encoding(AtMostOneEncoding::Commander);
+/// encoder.add_constraint(constraint);
+///
+/// let model = encoder.solve().unwrap();
+/// let true_lits = model.vars().filter(|l| l.is_pos()).count();
+/// assert!(true_lits <= 2);
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AtMostOne<I> {
+    pub lits: I,
+}
+
+impl<I> AtMostOne<I> {
+    /// Selects the encoding of the constraint.
+    pub fn with_encoding(
+        self,
+        encoding: AtMostOneEncoding,
+    ) -> Encoded<Self, AtMostOneEncoding> {
+        Encoded {
+            constraint: self,
+            encoding,
+        }
+    }
+}
+
+impl<V, I> Constraint<V> for AtMostOne<I>
+where
+    V: SatVar,
+    I: Iterator + Clone,
+    I::Item: Into<VarType<V>> + Debug,
+{
+    fn encode<S: Backend>(self, solver: &mut S, varmap: &mut VarMap<V>) {
+        let encoding = AtMostOneEncoding::select(self.lits.clone().count());
+        self.with_encoding(encoding).encode(solver, varmap)
+    }
+}
+
+impl<V, I> ConstraintRepr<V> for AtMostOne<I>
+where
+    V: SatVar,
+    I: Iterator + Clone,
+    I::Item: Into<VarType<V>> + Debug,
+{
+    fn encode_constraint_implies_repr<S: Backend>(
+        self,
+        repr: Option<i32>,
+        solver: &mut S,
+        varmap: &mut VarMap<V>,
+    ) -> i32 {
+        let encoding = AtMostOneEncoding::select(self.lits.clone().count());
+        self.with_encoding(encoding)
+            .encode_constraint_implies_repr(repr, solver, varmap)
+    }
+}
+
+impl<V, I> Constraint<V> for Encoded<AtMostOne<I>, AtMostOneEncoding>
+where
+    V: SatVar,
+    I: Iterator + Clone,
+    I::Item: Into<VarType<V>> + Debug,
+{
+    fn encode<S: Backend>(self, solver: &mut S, varmap: &mut VarMap<V>) {
+        let lits: Vec<_> = self.constraint.lits.map(|l| varmap.add_var(l)).collect();
+
+        let mut encoder = AtMostOneEncoder { solver, varmap };
+        match self.encoding {
+            AtMostOneEncoding::Pairwise => encoder.pairwise(&lits),
+            AtMostOneEncoding::Sequential => encoder.sequential(&lits),
+            AtMostOneEncoding::Commander => encoder.commander(&lits),
+            AtMostOneEncoding::Product => encoder.product(&lits),
+            AtMostOneEncoding::Bimander => encoder.bimander(&lits),
+        }
+    }
+}
+
+impl<V, I> ConstraintRepr<V> for Encoded<AtMostOne<I>, AtMostOneEncoding>
+where
+    V: SatVar,
+    I: Iterator + Clone,
+    I::Item: Into<VarType<V>> + Debug,
+{
+    /// `repr` is false only if one of the auxiliary variables of the encoding
+    /// witnesses two true literals.
+    fn encode_constraint_implies_repr<S: Backend>(
+        self,
+        repr: Option<i32>,
+        solver: &mut S,
+        varmap: &mut VarMap<V>,
+    ) -> i32 {
+        let lits: Vec<_> = self.constraint.lits.map(|l| varmap.add_var(l)).collect();
+        let repr = repr.unwrap_or_else(|| varmap.new_var());
+
+        let mut encoder = AtMostOneEncoder { solver, varmap };
+        let witnesses = match self.encoding {
+            AtMostOneEncoding::Pairwise => encoder.pairwise_witnesses(&lits),
+            AtMostOneEncoding::Sequential => encoder.sequential_witnesses(&lits),
+            AtMostOneEncoding::Commander => encoder.commander_witnesses(&lits),
+            AtMostOneEncoding::Product => encoder.product_witnesses(&lits),
+            AtMostOneEncoding::Bimander => encoder.bimander_witnesses(&lits),
+        };
+        encoder
+            .solver
+            .add_clause(witnesses.into_iter().chain(clause![repr]));
+
+        repr
+    }
+}
+
+impl<I> Debug for AtMostOne<I>
+where
+    I: Iterator + Clone,
+    I::Item: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lits: Vec<_> = self.lits.clone().collect();
+
+        f.debug_struct("AtMostOne").field("vars", &lits).finish()
+    }
+}
+
+struct AtMostOneEncoder<'a, V, S> {
+    solver: &'a mut S,
+    varmap: &'a mut VarMap<V>,
+}
+
+impl<V: SatVar, S: Backend> AtMostOneEncoder<'_, V, S> {
+    fn pairwise(&mut self, lits: &[i32]) {
+        for (i, &a) in lits.iter().enumerate() {
+            for &b in &lits[i + 1..] {
+                self.solver.add_clause(clause![-a, -b]);
+            }
+        }
+    }
+
+    fn sequential(&mut self, lits: &[i32]) {
+        // `s` is true if one of the literals up to the current one is true.
+        let mut prev_s: Option<i32> = None;
+
+        for (i, &lit) in lits.iter().enumerate() {
+            if let Some(prev_s) = prev_s {
+                self.solver.add_clause(clause![-lit, -prev_s]);
+            }
+
+            if i + 1 < lits.len() {
+                let s = self.varmap.new_var();
+                self.solver.add_clause(clause![-lit, s]);
+
+                if let Some(prev_s) = prev_s {
+                    self.solver.add_clause(clause![-prev_s, s]);
+                }
+
+                prev_s = Some(s);
+            }
+        }
+    }
+
+    fn commander(&mut self, lits: &[i32]) {
+        if lits.len() <= 3 {
+            return self.pairwise(lits);
+        }
+
+        let commanders: Vec<_> = lits
+            .chunks(3)
+            .map(|group| {
+                let commander = self.varmap.new_var();
+
+                self.pairwise(group);
+                for &lit in group {
+                    self.solver.add_clause(clause![-lit, commander]);
+                }
+                self.solver
+                    .add_clause(group.iter().copied().chain(clause![-commander]));
+
+                commander
+            })
+            .collect();
+
+        self.commander(&commanders);
+    }
+
+    fn product(&mut self, lits: &[i32]) {
+        if lits.len() <= 6 {
+            return self.pairwise(lits);
+        }
+
+        let columns = (lits.len() as f64).sqrt().ceil() as usize;
+        let rows = lits.len().div_ceil(columns);
+
+        let row_vars: Vec<_> = (0..rows).map(|_| self.varmap.new_var()).collect();
+        let column_vars: Vec<_> =
+            (0..columns).map(|_| self.varmap.new_var()).collect();
+
+        for (i, &lit) in lits.iter().enumerate() {
+            self.solver.add_clause(clause![-lit, row_vars[i / columns]]);
+            self.solver
+                .add_clause(clause![-lit, column_vars[i % columns]]);
+        }
+
+        self.product(&row_vars);
+        self.product(&column_vars);
+    }
+
+    fn bimander(&mut self, lits: &[i32]) {
+        let groups = lits.len().div_ceil(2);
+        let bits = (usize::BITS - groups.saturating_sub(1).leading_zeros()) as usize;
+        let bit_vars: Vec<_> = (0..bits).map(|_| self.varmap.new_var()).collect();
+
+        // Every group is identified by the binary number of its index.
+        for (i, group) in lits.chunks(2).enumerate() {
+            self.pairwise(group);
+
+            for &lit in group {
+                for (bit, &var) in bit_vars.iter().enumerate() {
+                    let var = if i & (1 << bit) != 0 { var } else { -var };
+                    self.solver.add_clause(clause![-lit, var]);
+                }
+            }
+        }
+    }
+
+    // The `*_witnesses` functions reify the encodings. Their auxiliary variables
+    // are only true if one of the literals they stand for is true, and each
+    // returned witness is only true if two of `lits` are true.
+
+    /// Returns a variable which is only true if one of `a` and one of `b` is true.
+    fn both(&mut self, a: &[i32], b: &[i32]) -> i32 {
+        let witness = self.varmap.new_var();
+        self.solver
+            .add_clause(a.iter().copied().chain(clause![-witness]));
+        self.solver
+            .add_clause(b.iter().copied().chain(clause![-witness]));
+        witness
+    }
+
+    /// Returns a variable which is only true if one of `lits` is true.
+    fn any(&mut self, lits: &[i32]) -> i32 {
+        let var = self.varmap.new_var();
+        self.solver
+            .add_clause(lits.iter().copied().chain(clause![-var]));
+        var
+    }
+
+    fn pairwise_witnesses(&mut self, lits: &[i32]) -> Vec<i32> {
+        let mut witnesses = Vec::new();
+        for (i, &a) in lits.iter().enumerate() {
+            for &b in &lits[i + 1..] {
+                witnesses.push(self.both(&[a], &[b]));
+            }
+        }
+        witnesses
+    }
+
+    fn sequential_witnesses(&mut self, lits: &[i32]) -> Vec<i32> {
+        let mut witnesses = Vec::new();
+        let mut prev_s: Option<i32> = None;
+
+        for (i, &lit) in lits.iter().enumerate() {
+            if let Some(prev_s) = prev_s {
+                witnesses.push(self.both(&[lit], &[prev_s]));
+            }
+
+            if i + 1 < lits.len() {
+                let s = match prev_s {
+                    Some(prev_s) => self.any(&[lit, prev_s]),
+                    None => lit,
+                };
+                prev_s = Some(s);
+            }
+        }
+        witnesses
+    }
+
+    fn commander_witnesses(&mut self, lits: &[i32]) -> Vec<i32> {
+        if lits.len() <= 3 {
+            return self.pairwise_witnesses(lits);
+        }
+
+        let mut witnesses = Vec::new();
+        let commanders: Vec<_> = lits
+            .chunks(3)
+            .map(|group| {
+                witnesses.extend(self.pairwise_witnesses(group));
+                self.any(group)
+            })
+            .collect();
+
+        witnesses.extend(self.commander_witnesses(&commanders));
+        witnesses
+    }
+
+    fn product_witnesses(&mut self, lits: &[i32]) -> Vec<i32> {
+        if lits.len() <= 6 {
+            return self.pairwise_witnesses(lits);
+        }
+
+        let columns = (lits.len() as f64).sqrt().ceil() as usize;
+
+        let row_vars: Vec<_> =
+            lits.chunks(columns).map(|row| self.any(row)).collect();
+        let column_vars: Vec<_> = (0..columns)
+            .map(|j| {
+                let column: Vec<_> =
+                    lits.iter().copied().skip(j).step_by(columns).collect();
+                self.any(&column)
+            })
+            .collect();
+
+        // Two true literals are in different rows or in different columns.
+        let mut witnesses = self.product_witnesses(&row_vars);
+        witnesses.extend(self.product_witnesses(&column_vars));
+        witnesses
+    }
+
+    fn bimander_witnesses(&mut self, lits: &[i32]) -> Vec<i32> {
+        let groups = lits.len().div_ceil(2);
+        let bits = (usize::BITS - groups.saturating_sub(1).leading_zeros()) as usize;
+
+        let mut witnesses = Vec::new();
+        for group in lits.chunks(2) {
+            witnesses.extend(self.pairwise_witnesses(group));
+        }
+
+        // Two true literals in different groups differ in one bit of the index.
+        for bit in 0..bits {
+            let (mut ones, mut zeros) = (Vec::new(), Vec::new());
+            for (i, group) in lits.chunks(2).enumerate() {
+                if i & (1 << bit) != 0 {
+                    ones.extend_from_slice(group);
+                } else {
+                    zeros.extend_from_slice(group);
+                }
+            }
+            witnesses.push(self.both(&ones, &zeros));
+        }
+        witnesses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constraints::test_util::{
+            constraint_equals_repr_tester, constraint_implies_repr_tester,
+            retry_until_unsat,
+        },
+        CadicalEncoder,
+    };
+
+    const ENCODINGS: [AtMostOneEncoding; 5] = [
+        AtMostOneEncoding::Pairwise,
+        AtMostOneEncoding::Sequential,
+        AtMostOneEncoding::Commander,
+        AtMostOneEncoding::Product,
+        AtMostOneEncoding::Bimander,
+    ];
+
+    #[test]
+    fn at_most_one_encodings() {
+        for &encoding in &ENCODINGS {
+            for range in 1..=12 {
+                let mut encoder = CadicalEncoder::<u32>::new();
+                let lits = 0..range;
+                encoder.add_constraint(AtMostOne { lits }.with_encoding(encoding));
+
+                let res = retry_until_unsat(&mut encoder, |model| {
+                    assert!(model.vars().filter(|l| l.is_pos()).count() <= 1)
+                });
+                assert_eq!(res as u32, range + 1, "{:?} {}", encoding, range);
+            }
+        }
+    }
+
+    #[test]
+    fn at_most_one_selected_by_size() {
+        for &range in &[4, 20, 40] {
+            let mut encoder = CadicalEncoder::<u32>::new();
+            encoder.add_constraint(AtMostOne { lits: 0..range });
+
+            let res = retry_until_unsat(&mut encoder, |model| {
+                assert!(model.vars().filter(|l| l.is_pos()).count() <= 1)
+            });
+            assert_eq!(res as u32, range + 1);
+        }
+    }
+
+    #[test]
+    fn at_most_one_implies_repr() {
+        let range = 8;
+
+        for &encoding in &ENCODINGS {
+            let mut encoder = CadicalEncoder::<u32>::new();
+
+            let repr = AtMostOne { lits: 0..range }
+                .with_encoding(encoding)
+                .encode_constraint_implies_repr(
+                    None,
+                    &mut encoder.backend,
+                    &mut encoder.varmap,
+                );
+
+            let res = constraint_implies_repr_tester(&mut encoder, repr, |model| {
+                model.vars().filter(|l| l.is_pos()).count() <= 1
+            });
+            assert_eq!(res.correct as u32, range + 1, "{:?}", encoding);
+            assert_eq!(res.total(), 1 << range);
+        }
+    }
+
+    #[test]
+    fn at_most_one_equals_repr() {
+        let range = 8;
+
+        for &encoding in &ENCODINGS {
+            let mut encoder = CadicalEncoder::<u32>::new();
+
+            let repr = AtMostOne { lits: 0..range }
+                .with_encoding(encoding)
+                .encode_constraint_equals_repr(
+                    None,
+                    &mut encoder.backend,
+                    &mut encoder.varmap,
+                );
+
+            let res = constraint_equals_repr_tester(&mut encoder, repr, |model| {
+                model.vars().filter(|l| l.is_pos()).count() <= 1
+            });
+            assert_eq!(res.correct as u32, range + 1);
+            assert_eq!(res.total(), 1 << range);
+        }
+    }
+}
@@ -1,6 +1,10 @@
 use core::fmt;
 use std::{fmt::Debug, iter};
 
+use super::{
+    sorting::{encode_cardinality_network, encode_sorting_network},
+    totalizer::encode_totalizer,
+};
 use crate::{
     circuit::{Circuit, Direction},
     clause, Backend, Constraint, ConstraintRepr, SatVar, VarMap, VarType,
@@ -9,7 +13,7 @@ use crate::{
 /// Encodes a sequential counter used for all cardinality constraint types.
 /// Returns the k output vars which different constraints can constrain to
 /// achieve their respective behaviour.
-fn encode_cardinality_constraint<V, S, I>(
+pub(crate) fn encode_cardinality_constraint<V, S, I>(
     lits: I,
     k: u32,
     dir: Direction,
@@ -84,6 +88,111 @@ where
     prev_s
 }
 
+/// Encoding of the unary counter used by the cardinality constraints.
+///
+/// The sequential counter needs `O(n·k)` clauses, the totalizer `O(n²)` and the
+/// k-simplified totalizer `O(n·k)` with fewer auxiliary variables for small `k`.
+/// The sorting network needs `O(n log² n)` clauses and the cardinality network
+/// `O(n log² k)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CardinalityEncoding {
+    #[default]
+    SequentialCounter,
+    Totalizer,
+    /// Totalizer whose nodes only count up to the bound of the constraint.
+    KTotalizer,
+    /// Batcher odd-even merge sorting network.
+    SortingNetwork,
+    /// Cardinality network of Asín et al., which only sorts up to the bound of the
+    /// constraint.
+    CardinalityNetwork,
+}
+
+/// Encodes the unary count of `lits` with `encoding`.
+/// Returns `k` outputs, the output at index `i` stands for at least `i + 1` true
+/// literals.
+pub(crate) fn encode_counter<V, S, I>(
+    lits: I,
+    k: u32,
+    dir: Direction,
+    encoding: CardinalityEncoding,
+    solver: &mut S,
+    varmap: &mut VarMap<V>,
+) -> Vec<i32>
+where
+    V: SatVar,
+    S: Backend,
+    I: Iterator,
+    I::Item: Into<VarType<V>>,
+{
+    if encoding == CardinalityEncoding::SequentialCounter {
+        return encode_cardinality_constraint(lits, k, dir, None, solver, varmap);
+    }
+
+    let vars: Vec<_> = lits.map(|v| varmap.add_var(v)).collect();
+    let k = k as usize;
+
+    let out: Vec<_> = match encoding {
+        CardinalityEncoding::SequentialCounter => unreachable!(),
+        CardinalityEncoding::Totalizer => {
+            encode_totalizer(&vars, usize::MAX, dir, solver, varmap)
+                .into_iter()
+                .map(Some)
+                .collect()
+        }
+        CardinalityEncoding::KTotalizer => {
+            encode_totalizer(&vars, k, dir, solver, varmap)
+                .into_iter()
+                .map(Some)
+                .collect()
+        }
+        CardinalityEncoding::SortingNetwork => {
+            encode_sorting_network(&vars, dir, solver, varmap)
+        }
+        CardinalityEncoding::CardinalityNetwork => {
+            encode_cardinality_network(&vars, k, dir, solver, varmap)
+        }
+    };
+
+    // Outputs for more true literals than there are inputs are always false.
+    (0..k)
+        .map(|i| match out.get(i).copied().flatten() {
+            Some(o) => o,
+            None => {
+                let o = varmap.new_var();
+                solver.add_clause(clause![-o]);
+                o
+            }
+        })
+        .collect()
+}
+
+/// Constraint together with the encoding used for it, created by `with_encoding`.
+///
+/// # Example
+/// ```rust
+/// # use satoxid::{CadicalEncoder, constraints::{AtMostK, CardinalityEncoding}};
+/// # fn main() {
+/// # let mut encoder = CadicalEncoder::new();
+/// let constraint = AtMostK {
+///     k: 2,
+///     lits: vec!["a", "b", "c", "d"].into_iter(),
+/// }
+/// .with_encoding(CardinalityEncoding::Totalizer);
+///
+/// encoder.add_constraint(constraint);
+///
+/// let model = encoder.solve().unwrap();
+/// let true_lits = model.vars().filter(|l| l.is_pos()).count();
+/// assert!(true_lits <= 2);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Encoded<C, E = CardinalityEncoding> {
+    pub constraint: C,
+    pub encoding: E,
+}
+
 /// This constraint encodes the requirement that at most `k` of `lits` are true.
 ///
 /// # Example
@@ -109,6 +218,16 @@ pub struct AtMostK<I> {
     pub k: u32,
 }
 
+impl<I> AtMostK<I> {
+    /// Selects the encoding of the constraint.
+    pub fn with_encoding(self, encoding: CardinalityEncoding) -> Encoded<Self> {
+        Encoded {
+            constraint: self,
+            encoding,
+        }
+    }
+}
+
 impl<V, I> Constraint<V> for AtMostK<I>
 where
     V: SatVar,
@@ -116,17 +235,56 @@ where
     I::Item: Into<VarType<V>> + Debug,
 {
     fn encode<S: Backend>(self, solver: &mut S, varmap: &mut VarMap<V>) {
-        if self.k == 0 {
-            for v in self.lits {
+        self.with_encoding(CardinalityEncoding::default())
+            .encode(solver, varmap)
+    }
+}
+
+impl<V, I> ConstraintRepr<V> for AtMostK<I>
+where
+    V: SatVar,
+    I: Iterator + Clone,
+    I::Item: Into<VarType<V>> + Debug,
+{
+    fn encode_constraint_implies_repr<S: Backend>(
+        self,
+        repr: Option<i32>,
+        solver: &mut S,
+        varmap: &mut VarMap<V>,
+    ) -> i32 {
+        self.with_encoding(CardinalityEncoding::default())
+            .encode_constraint_implies_repr(repr, solver, varmap)
+    }
+
+    fn encode_constraint_equals_repr<S: Backend>(
+        self,
+        repr: Option<i32>,
+        solver: &mut S,
+        varmap: &mut VarMap<V>,
+    ) -> i32 {
+        self.with_encoding(CardinalityEncoding::default())
+            .encode_constraint_equals_repr(repr, solver, varmap)
+    }
+}
+
+impl<V, I> Constraint<V> for Encoded<AtMostK<I>>
+where
+    V: SatVar,
+    I: Iterator + Clone,
+    I::Item: Into<VarType<V>> + Debug,
+{
+    fn encode<S: Backend>(self, solver: &mut S, varmap: &mut VarMap<V>) {
+        if self.constraint.k == 0 {
+            for v in self.constraint.lits {
                 let v = varmap.add_var(v);
                 solver.add_clause(clause![-v]);
             }
         } else {
-            let out = encode_cardinality_constraint(
-                self.lits,
-                self.k + 1,
+            let out = encode_counter(
+                self.constraint.lits,
+                self.constraint.k + 1,
                 Direction::InToOut,
-                None,
+                self.encoding,
                 solver,
                 varmap,
             );
@@ -136,7 +294,7 @@ where
     }
 }
 
-impl<V, I> ConstraintRepr<V> for AtMostK<I>
+impl<V, I> ConstraintRepr<V> for Encoded<AtMostK<I>>
 where
     V: SatVar,
     I: Iterator + Clone,
@@ -148,19 +306,19 @@ where
         solver: &mut S,
         varmap: &mut VarMap<V>,
     ) -> i32 {
-        if self.k == 0 {
+        if self.constraint.k == 0 {
             let repr = repr.unwrap_or_else(|| varmap.new_var());
 
-            let lits = self.lits.map(|lit| varmap.add_var(lit));
+            let lits = self.constraint.lits.map(|lit| varmap.add_var(lit));
             solver.add_clause(lits.chain(clause![repr]));
 
             repr
         } else {
-            let out = encode_cardinality_constraint(
-                self.lits,
-                self.k + 1,
+            let out = encode_counter(
+                self.constraint.lits,
+                self.constraint.k + 1,
                 Direction::OutToIn,
-                None,
+                self.encoding,
                 solver,
                 varmap,
             );
@@ -182,24 +340,24 @@ where
         solver: &mut S,
         varmap: &mut VarMap<V>,
     ) -> i32 {
-        if self.k == 0 {
+        if self.constraint.k == 0 {
             let repr = repr.unwrap_or_else(|| varmap.new_var());
 
-            let lits = self.lits.clone().map(|lit| varmap.add_var(lit));
+            let lits = self.constraint.lits.clone().map(|lit| varmap.add_var(lit));
             solver.add_clause(lits.chain(clause![repr]));
 
-            let lits = self.lits.map(|lit| varmap.add_var(lit));
+            let lits = self.constraint.lits.map(|lit| varmap.add_var(lit));
             for lit in lits {
                 solver.add_clause(clause![-lit, -repr])
             }
 
             repr
         } else {
-            let out = encode_cardinality_constraint(
-                self.lits,
-                self.k + 1,
+            let out = encode_counter(
+                self.constraint.lits,
+                self.constraint.k + 1,
                 Direction::Both,
-                None,
+                self.encoding,
                 solver,
                 varmap,
             );
@@ -257,6 +415,16 @@ pub struct AtLeastK<I> {
     pub k: u32,
 }
 
+impl<I> AtLeastK<I> {
+    /// Selects the encoding of the constraint.
+    pub fn with_encoding(self, encoding: CardinalityEncoding) -> Encoded<Self> {
+        Encoded {
+            constraint: self,
+            encoding,
+        }
+    }
+}
+
 impl<V, I> Constraint<V> for AtLeastK<I>
 where
     V: SatVar,
@@ -264,12 +432,51 @@ where
     I::Item: Into<VarType<V>> + Debug,
 {
     fn encode<S: Backend>(self, solver: &mut S, varmap: &mut VarMap<V>) {
-        if self.k != 0 {
-            let out = encode_cardinality_constraint(
-                self.lits,
-                self.k,
+        self.with_encoding(CardinalityEncoding::default())
+            .encode(solver, varmap)
+    }
+}
+
+impl<V, I> ConstraintRepr<V> for AtLeastK<I>
+where
+    V: SatVar,
+    I: Iterator + Clone,
+    I::Item: Into<VarType<V>> + Debug,
+{
+    fn encode_constraint_implies_repr<S: Backend>(
+        self,
+        repr: Option<i32>,
+        solver: &mut S,
+        varmap: &mut VarMap<V>,
+    ) -> i32 {
+        self.with_encoding(CardinalityEncoding::default())
+            .encode_constraint_implies_repr(repr, solver, varmap)
+    }
+
+    fn encode_constraint_equals_repr<S: Backend>(
+        self,
+        repr: Option<i32>,
+        solver: &mut S,
+        varmap: &mut VarMap<V>,
+    ) -> i32 {
+        self.with_encoding(CardinalityEncoding::default())
+            .encode_constraint_equals_repr(repr, solver, varmap)
+    }
+}
+
+impl<V, I> Constraint<V> for Encoded<AtLeastK<I>>
+where
+    V: SatVar,
+    I: Iterator + Clone,
+    I::Item: Into<VarType<V>> + Debug,
+{
+    fn encode<S: Backend>(self, solver: &mut S, varmap: &mut VarMap<V>) {
+        if self.constraint.k != 0 {
+            let out = encode_counter(
+                self.constraint.lits,
+                self.constraint.k,
                 Direction::OutToIn,
-                None,
+                self.encoding,
                 solver,
                 varmap,
             );
@@ -279,7 +486,7 @@ where
     }
 }
 
-impl<V, I> ConstraintRepr<V> for AtLeastK<I>
+impl<V, I> ConstraintRepr<V> for Encoded<AtLeastK<I>>
 where
     V: SatVar,
     I: Iterator + Clone,
@@ -291,18 +498,18 @@ where
         solver: &mut S,
         varmap: &mut VarMap<V>,
     ) -> i32 {
-        if self.k == 0 {
+        if self.constraint.k == 0 {
             let repr = repr.unwrap_or_else(|| varmap.new_var());
 
             solver.add_clause(clause![repr]);
 
             repr
         } else {
-            let out = encode_cardinality_constraint(
-                self.lits,
-                self.k,
+            let out = encode_counter(
+                self.constraint.lits,
+                self.constraint.k,
                 Direction::InToOut,
-                None,
+                self.encoding,
                 solver,
                 varmap,
             );
@@ -324,18 +531,18 @@ where
         solver: &mut S,
         varmap: &mut VarMap<V>,
     ) -> i32 {
-        if self.k == 0 {
+        if self.constraint.k == 0 {
             let repr = repr.unwrap_or_else(|| varmap.new_var());
 
             solver.add_clause(clause![repr]);
 
             repr
         } else {
-            let out = encode_cardinality_constraint(
-                self.lits,
-                self.k,
+            let out = encode_counter(
+                self.constraint.lits,
+                self.constraint.k,
                 Direction::Both,
-                None,
+                self.encoding,
                 solver,
                 varmap,
             );
@@ -393,6 +600,16 @@ pub struct ExactlyK<I> {
     pub k: u32,
 }
 
+impl<I> ExactlyK<I> {
+    /// Selects the encoding of the constraint.
+    pub fn with_encoding(self, encoding: CardinalityEncoding) -> Encoded<Self> {
+        Encoded {
+            constraint: self,
+            encoding,
+        }
+    }
+}
+
 impl<V, I> Constraint<V> for ExactlyK<I>
 where
     V: SatVar,
@@ -400,17 +617,56 @@ where
     I::Item: Into<VarType<V>> + Debug,
 {
     fn encode<S: Backend>(self, solver: &mut S, varmap: &mut VarMap<V>) {
-        if self.k == 0 {
-            for v in self.lits {
+        self.with_encoding(CardinalityEncoding::default())
+            .encode(solver, varmap)
+    }
+}
+
+impl<V, I> ConstraintRepr<V> for ExactlyK<I>
+where
+    V: SatVar,
+    I: Iterator + Clone,
+    I::Item: Into<VarType<V>> + Debug,
+{
+    fn encode_constraint_implies_repr<S: Backend>(
+        self,
+        repr: Option<i32>,
+        solver: &mut S,
+        varmap: &mut VarMap<V>,
+    ) -> i32 {
+        self.with_encoding(CardinalityEncoding::default())
+            .encode_constraint_implies_repr(repr, solver, varmap)
+    }
+
+    fn encode_constraint_equals_repr<S: Backend>(
+        self,
+        repr: Option<i32>,
+        solver: &mut S,
+        varmap: &mut VarMap<V>,
+    ) -> i32 {
+        self.with_encoding(CardinalityEncoding::default())
+            .encode_constraint_equals_repr(repr, solver, varmap)
+    }
+}
+
+impl<V, I> Constraint<V> for Encoded<ExactlyK<I>>
+where
+    V: SatVar,
+    I: Iterator + Clone,
+    I::Item: Into<VarType<V>> + Debug,
+{
+    fn encode<S: Backend>(self, solver: &mut S, varmap: &mut VarMap<V>) {
+        if self.constraint.k == 0 {
+            for v in self.constraint.lits {
                 let v = varmap.add_var(v);
                 solver.add_clause(clause![-v]);
             }
         } else {
-            let out = encode_cardinality_constraint(
-                self.lits,
-                self.k + 1,
+            let out = encode_counter(
+                self.constraint.lits,
+                self.constraint.k + 1,
                 Direction::Both,
-                None,
+                self.encoding,
                 solver,
                 varmap,
             );
@@ -421,7 +677,7 @@ where
     }
 }
 
-impl<V, I> ConstraintRepr<V> for ExactlyK<I>
+impl<V, I> ConstraintRepr<V> for Encoded<ExactlyK<I>>
 where
     V: SatVar,
     I: Iterator + Clone,
@@ -435,15 +691,15 @@ where
     ) -> i32 {
         let repr = repr.unwrap_or_else(|| varmap.new_var());
 
-        if self.k == 0 {
-            let lits = self.lits.map(|lit| varmap.add_var(lit));
+        if self.constraint.k == 0 {
+            let lits = self.constraint.lits.map(|lit| varmap.add_var(lit));
             solver.add_clause(lits.chain(clause![repr]));
         } else {
-            let out = encode_cardinality_constraint(
-                self.lits,
-                self.k + 1,
+            let out = encode_counter(
+                self.constraint.lits,
+                self.constraint.k + 1,
                 Direction::Both,
-                None,
+                self.encoding,
                 solver,
                 varmap,
             );
@@ -465,20 +721,20 @@ where
     ) -> i32 {
         let repr = repr.unwrap_or_else(|| varmap.new_var());
 
-        if self.k == 0 {
-            let lits = self.lits.clone().map(|lit| varmap.add_var(lit));
+        if self.constraint.k == 0 {
+            let lits = self.constraint.lits.clone().map(|lit| varmap.add_var(lit));
             solver.add_clause(lits.chain(clause![repr]));
 
-            let lits = self.lits.map(|lit| varmap.add_var(lit));
+            let lits = self.constraint.lits.map(|lit| varmap.add_var(lit));
             for lit in lits {
                 solver.add_clause(clause![-lit, -repr])
             }
         } else {
-            let out = encode_cardinality_constraint(
-                self.lits,
-                self.k + 1,
+            let out = encode_counter(
+                self.constraint.lits,
+                self.constraint.k + 1,
                 Direction::Both,
-                None,
+                self.encoding,
                 solver,
                 varmap,
             );
@@ -549,36 +805,79 @@ impl<V> SameCardinality<V> {
     }
 }
 
+impl<V> SameCardinality<V> {
+    /// Selects the encoding of the constraint.
+    pub fn with_encoding(self, encoding: CardinalityEncoding) -> Encoded<Self> {
+        Encoded {
+            constraint: self,
+            encoding,
+        }
+    }
+}
+
 impl<V: SatVar> Constraint<V> for SameCardinality<V> {
     fn encode<S: Backend>(self, solver: &mut S, varmap: &mut VarMap<V>) {
-        if self.lits.is_empty() {
-            return;
-        }
+        self.with_encoding(CardinalityEncoding::default())
+            .encode(solver, varmap)
+    }
+}
 
-        let max = self.lits.iter().map(|l| l.len()).max().unwrap();
-        let min = self.lits.iter().map(|l| l.len()).min().unwrap();
+impl<V: SatVar> ConstraintRepr<V> for SameCardinality<V> {
+    fn encode_constraint_implies_repr<S: Backend>(
+        self,
+        repr: Option<i32>,
+        solver: &mut S,
+        varmap: &mut VarMap<V>,
+    ) -> i32 {
+        self.with_encoding(CardinalityEncoding::default())
+            .encode_constraint_implies_repr(repr, solver, varmap)
+    }
 
-        let repr: Vec<_> = (0..max).map(|_| varmap.new_var()).collect();
+    fn encode_constraint_equals_repr<S: Backend>(
+        self,
+        repr: Option<i32>,
+        solver: &mut S,
+        varmap: &mut VarMap<V>,
+    ) -> i32 {
+        self.with_encoding(CardinalityEncoding::default())
+            .encode_constraint_equals_repr(repr, solver, varmap)
+    }
+}
 
-        for v in repr.iter().rev().take(max - min) {
-            solver.add_clause(clause![-v]);
-        }
+impl<V: SatVar> Constraint<V> for Encoded<SameCardinality<V>> {
+    fn encode<S: Backend>(self, solver: &mut S, varmap: &mut VarMap<V>) {
+        let max = match self.constraint.lits.iter().map(|l| l.len()).max() {
+            Some(max) if max > 0 => max,
+            _ => return,
+        };
 
-        for lits in self.lits {
-            let k = lits.len();
-            encode_cardinality_constraint(
-                lits.into_iter(),
-                k as u32,
-                Direction::Both,
-                Some(&repr),
-                solver,
-                varmap,
-            );
+        let encoding = self.encoding;
+        let counts: Vec<_> = self
+            .constraint
+            .lits
+            .into_iter()
+            .map(|lits| {
+                encode_counter(
+                    lits.into_iter(),
+                    max as u32,
+                    Direction::Both,
+                    encoding,
+                    solver,
+                    varmap,
+                )
+            })
+            .collect();
+
+        let mut circuit = Circuit::new(solver, Direction::Both);
+        for pair in counts.windows(2) {
+            for (&a, &b) in pair[0].iter().zip(&pair[1]) {
+                circuit.equal(a, b);
+            }
         }
     }
 }
 
-impl<V: SatVar> ConstraintRepr<V> for SameCardinality<V> {
+impl<V: SatVar> ConstraintRepr<V> for Encoded<SameCardinality<V>> {
     fn encode_constraint_implies_repr<S: Backend>(
         self,
         repr: Option<i32>,
@@ -599,7 +898,7 @@ impl<V: SatVar> ConstraintRepr<V> for SameCardinality<V> {
 }
 
 fn encode_same_cardinality_repr<V: SatVar>(
-    constraint: SameCardinality<V>,
+    constraint: Encoded<SameCardinality<V>>,
     repr: Option<i32>,
     solver: &mut impl Backend,
     varmap: &mut VarMap<V>,
@@ -607,29 +906,22 @@ fn encode_same_cardinality_repr<V: SatVar>(
 ) -> i32 {
     let repr = repr.unwrap_or_else(|| varmap.new_var());
 
-    if constraint.lits.is_empty() {
-        solver.add_clause(clause!(repr));
-        return repr;
-    }
-
-    let max = constraint.lits.iter().map(|l| l.len()).max().unwrap();
+    let max = match constraint.constraint.lits.iter().map(|l| l.len()).max() {
+        Some(max) if max > 0 => max,
+        _ => {
+            solver.add_clause(clause!(repr));
+            return repr;
+        }
+    };
 
     let mut reprs = Vec::new();
 
-    for lits in constraint.lits {
-        let repr: Vec<_> = (0..max).map(|_| varmap.new_var()).collect();
-
-        let k = lits.len();
-
-        for v in repr.iter().rev().take(max - k) {
-            solver.add_clause(clause![-v]);
-        }
-
-        encode_cardinality_constraint(
+    for lits in constraint.constraint.lits {
+        let repr = encode_counter(
             lits.into_iter(),
-            k as u32,
+            max as u32,
             Direction::Both,
-            Some(&repr),
+            constraint.encoding,
             solver,
             varmap,
         );
@@ -790,7 +1082,7 @@ mod tests {
             },
             Equal,
         },
-        CadicalEncoder,
+        CadicalEncoder, Model,
     };
 
     #[test]
@@ -1281,6 +1573,162 @@ mod tests {
         assert_eq!(res.total(), 1 << range);
     }
 
+    const ENCODINGS: [CardinalityEncoding; 4] = [
+        CardinalityEncoding::Totalizer,
+        CardinalityEncoding::KTotalizer,
+        CardinalityEncoding::SortingNetwork,
+        CardinalityEncoding::CardinalityNetwork,
+    ];
+
+    #[test]
+    fn encoded_cardinality() {
+        let range = 9;
+        let k = 3;
+
+        for &encoding in &ENCODINGS {
+            let mut encoder = CadicalEncoder::<u32>::new();
+            let constraint = AtMostK { k, lits: 0..range };
+            encoder.add_constraint(constraint.with_encoding(encoding));
+            let res = retry_until_unsat(&mut encoder, |model| {
+                assert!(model.vars().filter(|l| l.is_pos()).count() <= k as usize)
+            });
+            assert_eq!(res as u32, (0..=k).map(|i| binomial(range, i)).sum::<u32>());
+
+            let mut encoder = CadicalEncoder::<u32>::new();
+            let constraint = AtLeastK { k, lits: 0..range };
+            encoder.add_constraint(constraint.with_encoding(encoding));
+            let res = retry_until_unsat(&mut encoder, |model| {
+                assert!(model.vars().filter(|l| l.is_pos()).count() >= k as usize)
+            });
+            assert_eq!(
+                res as u32,
+                (k..=range).map(|i| binomial(range, i)).sum::<u32>()
+            );
+
+            let mut encoder = CadicalEncoder::<u32>::new();
+            let constraint = ExactlyK { k, lits: 0..range };
+            encoder.add_constraint(constraint.with_encoding(encoding));
+            let res = retry_until_unsat(&mut encoder, |model| {
+                assert!(model.vars().filter(|l| l.is_pos()).count() == k as usize)
+            });
+            assert_eq!(res as u32, binomial(range, k));
+        }
+    }
+
+    #[test]
+    fn encoded_implies_repr() {
+        let range = 9;
+        let k = 3;
+        let count = |model: &Model<u32>| model.vars().filter(|l| l.is_pos()).count();
+
+        for &encoding in &ENCODINGS {
+            let mut encoder = CadicalEncoder::<u32>::new();
+            let repr = AtMostK { k, lits: 0..range }
+                .with_encoding(encoding)
+                .encode_constraint_implies_repr(
+                    None,
+                    &mut encoder.backend,
+                    &mut encoder.varmap,
+                );
+            let res = constraint_implies_repr_tester(&mut encoder, repr, |model| {
+                count(model) <= k as usize
+            });
+            assert_eq!(
+                res.correct as u32,
+                (0..=k).map(|i| binomial(range, i)).sum::<u32>()
+            );
+            assert_eq!(res.total(), 1 << range);
+
+            let mut encoder = CadicalEncoder::<u32>::new();
+            let repr = AtLeastK { k, lits: 0..range }
+                .with_encoding(encoding)
+                .encode_constraint_implies_repr(
+                    None,
+                    &mut encoder.backend,
+                    &mut encoder.varmap,
+                );
+            let res = constraint_implies_repr_tester(&mut encoder, repr, |model| {
+                count(model) >= k as usize
+            });
+            assert_eq!(
+                res.correct as u32,
+                (k..=range).map(|i| binomial(range, i)).sum::<u32>()
+            );
+            assert_eq!(res.total(), 1 << range);
+
+            let mut encoder = CadicalEncoder::<u32>::new();
+            let repr = ExactlyK { k, lits: 0..range }
+                .with_encoding(encoding)
+                .encode_constraint_implies_repr(
+                    None,
+                    &mut encoder.backend,
+                    &mut encoder.varmap,
+                );
+            let res = constraint_implies_repr_tester(&mut encoder, repr, |model| {
+                count(model) == k as usize
+            });
+            assert_eq!(res.correct as u32, binomial(range, k));
+            assert_eq!(res.total(), 1 << range);
+        }
+    }
+
+    #[test]
+    fn encoded_equals_repr() {
+        let range = 9;
+        let k = 3;
+        let count = |model: &Model<u32>| model.vars().filter(|l| l.is_pos()).count();
+
+        for &encoding in &ENCODINGS {
+            let mut encoder = CadicalEncoder::<u32>::new();
+            let repr = AtMostK { k, lits: 0..range }
+                .with_encoding(encoding)
+                .encode_constraint_equals_repr(
+                    None,
+                    &mut encoder.backend,
+                    &mut encoder.varmap,
+                );
+            let res = constraint_equals_repr_tester(&mut encoder, repr, |model| {
+                count(model) <= k as usize
+            });
+            assert_eq!(
+                res.correct as u32,
+                (0..=k).map(|i| binomial(range, i)).sum::<u32>()
+            );
+            assert_eq!(res.total(), 1 << range);
+
+            let mut encoder = CadicalEncoder::<u32>::new();
+            let repr = AtLeastK { k, lits: 0..range }
+                .with_encoding(encoding)
+                .encode_constraint_equals_repr(
+                    None,
+                    &mut encoder.backend,
+                    &mut encoder.varmap,
+                );
+            let res = constraint_equals_repr_tester(&mut encoder, repr, |model| {
+                count(model) >= k as usize
+            });
+            assert_eq!(
+                res.correct as u32,
+                (k..=range).map(|i| binomial(range, i)).sum::<u32>()
+            );
+            assert_eq!(res.total(), 1 << range);
+
+            let mut encoder = CadicalEncoder::<u32>::new();
+            let repr = ExactlyK { k, lits: 0..range }
+                .with_encoding(encoding)
+                .encode_constraint_equals_repr(
+                    None,
+                    &mut encoder.backend,
+                    &mut encoder.varmap,
+                );
+            let res = constraint_equals_repr_tester(&mut encoder, repr, |model| {
+                count(model) == k as usize
+            });
+            assert_eq!(res.correct as u32, binomial(range, k));
+            assert_eq!(res.total(), 1 << range);
+        }
+    }
+
     #[test]
     fn normal_same_cardinality() {
         let mut encoder = CadicalEncoder::<u32>::new();
@@ -1593,6 +2041,57 @@ mod tests {
         assert_eq!(res.total(), 1 << (2 * range));
     }
 
+    #[test]
+    fn encoded_same_cardinality() {
+        let range1: u32 = 3;
+        let range2: u32 = 5;
+        let same = |model: &Model<u32>| {
+            let c1 = (0..range1).filter(|&v| model[v]).count();
+            let c2 = (range1..range1 + range2).filter(|&v| model[v]).count();
+            c1 == c2
+        };
+        let expected = (0..=range1)
+            .map(|i| binomial(range1, i) * binomial(range2, i))
+            .sum::<u32>();
+
+        for &encoding in &ENCODINGS {
+            let mut constraint = SameCardinality::new();
+            constraint
+                .add_lits(0..range1)
+                .add_lits(range1..range1 + range2);
+
+            let mut encoder = CadicalEncoder::<u32>::new();
+            encoder.add_constraint(constraint.clone().with_encoding(encoding));
+            let res = retry_until_unsat(&mut encoder, |model| assert!(same(model)));
+            assert_eq!(res as u32, expected);
+
+            let mut encoder = CadicalEncoder::<u32>::new();
+            let repr = constraint
+                .clone()
+                .with_encoding(encoding)
+                .encode_constraint_implies_repr(
+                    None,
+                    &mut encoder.backend,
+                    &mut encoder.varmap,
+                );
+            let res = constraint_implies_repr_tester(&mut encoder, repr, same);
+            assert_eq!(res.correct as u32, expected);
+            assert_eq!(res.total(), 1 << (range1 + range2));
+
+            let mut encoder = CadicalEncoder::<u32>::new();
+            let repr = constraint
+                .with_encoding(encoding)
+                .encode_constraint_equals_repr(
+                    None,
+                    &mut encoder.backend,
+                    &mut encoder.varmap,
+                );
+            let res = constraint_equals_repr_tester(&mut encoder, repr, same);
+            assert_eq!(res.correct as u32, expected);
+            assert_eq!(res.total(), 1 << (range1 + range2));
+        }
+    }
+
     #[test]
     fn less_cardinality_constraint() {
         let mut encoder = CadicalEncoder::<u32>::new();
@@ -0,0 +1,195 @@
+use std::marker::PhantomData;
+
+use super::{cardinality::encode_counter, CardinalityEncoding};
+use crate::{circuit::Direction, clause, Backend, Encoder, SatVar, VarType};
+
+/// Unary count of a set of literals, which is encoded once and can be used by
+/// several constraints.
+///
+/// The literals returned by [`count_ge`](Counter::count_ge) and
+/// [`count_le`](Counter::count_le) are equivalent to the bound on the count, so they
+/// can be used in any constraint, e.g. [`If`](super::If), [`Iff`](super::Iff) or
+/// [`Expr`](super::Expr).
+///
+/// The count is encoded in the current scope of the encoder, it is removed with
+/// the scope.
+///
+/// # Example
+/// ```rust
+/// # use satoxid::{CadicalEncoder, constraints::{Counter, Iff}};
+/// # fn main() {
+/// let mut encoder = CadicalEncoder::new();
+///
+/// let counter = Counter::new(&mut encoder, vec!["a", "b", "c", "d"]);
+/// encoder.add_constraint(counter.count_ge(1));
+/// encoder.add_constraint(counter.count_le(2));
+///
+/// // "e" is true if and only if exactly two literals are true.
+/// let exactly_two = !counter.count_le(1);
+/// encoder.add_constraint(Iff { left: exactly_two, right: "e" });
+///
+/// let model = encoder.solve().unwrap();
+/// let count = ["a", "b", "c", "d"].iter().filter(|&&v| model[v]).count();
+/// assert!(1 <= count && count <= 2);
+/// assert_eq!(model["e"], count == 2);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Counter<V> {
+    /// `outputs[i]` is true if at least `i + 1` literals are true.
+    outputs: Vec<i32>,
+    /// Literal which is always true.
+    truth: i32,
+    var: PhantomData<V>,
+}
+
+impl<V: SatVar> Counter<V> {
+    /// Encodes the count of `lits` with the sequential counter.
+    pub fn new<S, I>(encoder: &mut Encoder<V, S>, lits: I) -> Self
+    where
+        S: Backend,
+        I: IntoIterator,
+        I::Item: Into<VarType<V>>,
+    {
+        Self::with_encoding(encoder, lits, CardinalityEncoding::default())
+    }
+
+    /// Encodes the count of `lits` with `encoding`.
+    pub fn with_encoding<S, I>(
+        encoder: &mut Encoder<V, S>,
+        lits: I,
+        encoding: CardinalityEncoding,
+    ) -> Self
+    where
+        S: Backend,
+        I: IntoIterator,
+        I::Item: Into<VarType<V>>,
+    {
+        let lits: Vec<VarType<V>> = lits.into_iter().map(Into::into).collect();
+        let (mut backend, varmap) = encoder.scoped();
+
+        let truth = varmap.new_var();
+        backend.add_clause(clause![truth]);
+
+        let outputs = if lits.is_empty() {
+            Vec::new()
+        } else {
+            let k = lits.len() as u32;
+            encode_counter(
+                lits.into_iter(),
+                k,
+                Direction::Both,
+                encoding,
+                &mut backend,
+                varmap,
+            )
+        };
+
+        Self {
+            outputs,
+            truth,
+            var: PhantomData,
+        }
+    }
+
+    /// Literal which is true if and only if at least `k` literals are true.
+    pub fn count_ge(&self, k: u32) -> VarType<V> {
+        let lit = match k as usize {
+            0 => self.truth,
+            k if k > self.outputs.len() => -self.truth,
+            k => self.outputs[k - 1],
+        };
+
+        VarType::Unnamed(lit)
+    }
+
+    /// Literal which is true if and only if at most `k` literals are true.
+    pub fn count_le(&self, k: u32) -> VarType<V> {
+        match k.checked_add(1) {
+            Some(k) => !self.count_ge(k),
+            None => VarType::Unnamed(self.truth),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_integer::binomial;
+
+    use super::*;
+    use crate::{
+        constraints::{test_util::retry_until_unsat, Expr, If, Iff},
+        CadicalEncoder,
+    };
+
+    #[test]
+    fn counter_bounds() {
+        let range = 6;
+
+        for &encoding in &[
+            CardinalityEncoding::SequentialCounter,
+            CardinalityEncoding::Totalizer,
+            CardinalityEncoding::SortingNetwork,
+        ] {
+            let mut encoder = CadicalEncoder::<u32>::new();
+            let counter = Counter::with_encoding(&mut encoder, 0..range, encoding);
+
+            encoder.add_constraint(counter.count_ge(2));
+            encoder.add_constraint(counter.count_le(3));
+            encoder.add_constraint(counter.count_le(range + 1));
+
+            let res = retry_until_unsat(&mut encoder, |model| {
+                let count = model.vars().filter(|l| l.is_pos()).count();
+                assert!((2..=3).contains(&count));
+            });
+            assert_eq!(res as u32, binomial(range, 2) + binomial(range, 3));
+        }
+    }
+
+    #[test]
+    fn counter_in_conditions() {
+        let range = 5;
+
+        let mut encoder = CadicalEncoder::<u32>::new();
+        let counter = Counter::new(&mut encoder, 0..range);
+
+        // If at least 3 are true, so is `range`.
+        encoder.add_constraint(If {
+            cond: counter.count_ge(3),
+            then: range,
+        });
+        // `range + 1` is true if and only if the count is 1 or 2.
+        let between = Expr::new(counter.count_ge(1)) & counter.count_le(2);
+        encoder.add_constraint(Iff {
+            left: between,
+            right: range + 1,
+        });
+
+        let res = retry_until_unsat(&mut encoder, |model| {
+            let count = (0..range).filter(|&v| model[v]).count();
+            if count >= 3 {
+                assert!(model[range]);
+            }
+            assert_eq!(model[range + 1], (1..=2).contains(&count));
+        });
+
+        // `range` is free if fewer than 3 literals are true.
+        let below: u32 = (0..3).map(|i| binomial(range, i)).sum();
+        assert_eq!(res as u32, (1 << range) + below);
+    }
+
+    #[test]
+    fn empty_counter() {
+        let mut encoder = CadicalEncoder::<u32>::new();
+        let counter = Counter::new(&mut encoder, Vec::<u32>::new());
+
+        encoder.push();
+        encoder.add_constraint(counter.count_ge(0));
+        encoder.add_constraint(counter.count_le(0));
+        assert!(encoder.solve().is_some());
+        encoder.pop();
+
+        encoder.add_constraint(counter.count_ge(1));
+        assert!(encoder.solve().is_none());
+    }
+}
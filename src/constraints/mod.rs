@@ -6,19 +6,29 @@ use std::fmt::Debug;
 use super::{Constraint, SatVar, VarMap};
 use crate::{Backend, ConstraintRepr, VarType};
 
+mod at_most_one;
 mod cardinality;
 mod conditional;
+mod counter;
 mod expr;
+mod pseudo_boolean;
+mod sorting;
+pub(crate) mod totalizer;
 pub(crate) mod util;
 
 #[cfg(test)]
 mod test_util;
 
+pub use at_most_one::{AtMostOne, AtMostOneEncoding};
+pub(crate) use cardinality::encode_cardinality_constraint;
 pub use cardinality::{
-    AtLeastK, AtMostK, ExactlyK, LessCardinality, SameCardinality,
+    AtLeastK, AtMostK, CardinalityEncoding, Encoded, ExactlyK, LessCardinality,
+    SameCardinality,
 };
 pub use conditional::{If, Iff};
+pub use counter::Counter;
 pub use expr::Expr;
+pub use pseudo_boolean::{Comparison, PbEncoding, PbLinear};
 
 #[doc(hidden)]
 #[macro_export]
@@ -0,0 +1,677 @@
+use core::fmt;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, VecDeque},
+    fmt::Debug,
+    ops,
+};
+
+use super::{totalizer::encode_generalized_totalizer, Encoded};
+use crate::{
+    circuit::Direction, Backend, Constraint, ConstraintRepr, SatVar, VarMap, VarType,
+};
+
+/// Comparison between the weighted sum and the bound of a [`PbLinear`] constraint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Comparison {
+    /// The sum is at most the bound.
+    Le,
+    /// The sum is at least the bound.
+    Ge,
+    /// The sum is equal to the bound.
+    Eq,
+}
+
+/// Encoding of a [`PbLinear`] constraint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PbEncoding {
+    /// Reduced ordered binary decision diagram, nodes are shared between bounds with
+    /// the same solutions.
+    #[default]
+    Bdd,
+    /// Generalized totalizer, its size depends on the number of distinct sums.
+    GeneralizedTotalizer,
+    /// Network of binary adders followed by a comparator. It needs only
+    /// `O(n log w)` clauses, but propagates less than the other encodings.
+    Adder,
+}
+
+/// This constraint encodes that the sum of the weights of the true literals in
+/// `terms` compares to `bound` as given by `cmp`.
+///
+/// Negative weights are allowed, they are normalized by negating their literals.
+/// The encoding can be chosen with `with_encoding`.
+///
+/// # Example
+/// ```rust
+/// # use satoxid::{CadicalEncoder, constraints::{Comparison, PbLinear}};
+/// # fn main() {
+/// # let mut encoder = CadicalEncoder::new();
+/// let constraint = PbLinear {
+///     terms: vec![(3, "a"), (2, "b"), (-4, "c"), (5, "d")].into_iter(),
+///     cmp: Comparison::Le,
+///     bound: 4,
+/// };
+///
+/// encoder.add_constraint(constraint);
+///
+/// let model = encoder.solve().unwrap();
+/// let sum = 3 * model["a"] as i64 + 2 * model["b"] as i64 - 4 * model["c"] as i64
+///     + 5 * model["d"] as i64;
+/// assert!(sum <= 4);
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct PbLinear<I> {
+    pub terms: I,
+    pub cmp: Comparison,
+    pub bound: i64,
+}
+
+impl<I> PbLinear<I> {
+    /// Selects the encoding of the constraint.
+    pub fn with_encoding(self, encoding: PbEncoding) -> Encoded<Self, PbEncoding> {
+        Encoded {
+            constraint: self,
+            encoding,
+        }
+    }
+}
+
+impl<I> PbLinear<I> {
+    /// The constraint as weighted sums of positive weights which have to be at most
+    /// their bound.
+    fn at_most<V, L>(self, varmap: &mut VarMap<V>) -> Vec<(Vec<(u64, i32)>, i64)>
+    where
+        V: SatVar,
+        I: Iterator<Item = (i64, L)>,
+        L: Into<VarType<V>>,
+    {
+        let mut bound = self.bound;
+        let terms: Vec<_> = self
+            .terms
+            .filter(|(weight, _)| *weight != 0)
+            .map(|(weight, lit)| {
+                let lit = varmap.add_var(lit);
+
+                // `w * l` is the same as `w + |w| * -l`.
+                if weight < 0 {
+                    bound -= weight;
+                    (weight.unsigned_abs(), -lit)
+                } else {
+                    (weight as u64, lit)
+                }
+            })
+            .collect();
+
+        let le = || (terms.clone(), bound);
+        let ge = || {
+            let total = terms.iter().map(|(weight, _)| weight).sum::<u64>();
+            let negated =
+                terms.iter().map(|&(weight, lit)| (weight, -lit)).collect();
+            (negated, total as i64 - bound)
+        };
+
+        match self.cmp {
+            Comparison::Le => vec![le()],
+            Comparison::Ge => vec![ge()],
+            Comparison::Eq => vec![le(), ge()],
+        }
+    }
+}
+
+impl<V, I, L> Constraint<V> for PbLinear<I>
+where
+    V: SatVar,
+    I: Iterator<Item = (i64, L)> + Clone,
+    L: Into<VarType<V>> + Debug,
+{
+    fn encode<S: Backend>(self, solver: &mut S, varmap: &mut VarMap<V>) {
+        self.with_encoding(PbEncoding::default())
+            .encode(solver, varmap)
+    }
+}
+
+impl<V, I, L> ConstraintRepr<V> for PbLinear<I>
+where
+    V: SatVar,
+    I: Iterator<Item = (i64, L)> + Clone,
+    L: Into<VarType<V>> + Debug,
+{
+    fn encode_constraint_implies_repr<S: Backend>(
+        self,
+        repr: Option<i32>,
+        solver: &mut S,
+        varmap: &mut VarMap<V>,
+    ) -> i32 {
+        self.with_encoding(PbEncoding::default())
+            .encode_constraint_implies_repr(repr, solver, varmap)
+    }
+
+    fn encode_constraint_equals_repr<S: Backend>(
+        self,
+        repr: Option<i32>,
+        solver: &mut S,
+        varmap: &mut VarMap<V>,
+    ) -> i32 {
+        self.with_encoding(PbEncoding::default())
+            .encode_constraint_equals_repr(repr, solver, varmap)
+    }
+}
+
+impl<V, I, L> Constraint<V> for Encoded<PbLinear<I>, PbEncoding>
+where
+    V: SatVar,
+    I: Iterator<Item = (i64, L)> + Clone,
+    L: Into<VarType<V>> + Debug,
+{
+    fn encode<S: Backend>(self, solver: &mut S, varmap: &mut VarMap<V>) {
+        let sums = self.constraint.at_most(varmap);
+
+        let mut encoder = PbEncoder::new(Direction::InToOut, solver, varmap);
+        for (terms, bound) in sums {
+            let overflow = encoder.overflow(&terms, bound, self.encoding);
+            encoder.clause(&[!overflow]);
+        }
+    }
+}
+
+impl<V, I, L> ConstraintRepr<V> for Encoded<PbLinear<I>, PbEncoding>
+where
+    V: SatVar,
+    I: Iterator<Item = (i64, L)> + Clone,
+    L: Into<VarType<V>> + Debug,
+{
+    fn encode_constraint_implies_repr<S: Backend>(
+        self,
+        repr: Option<i32>,
+        solver: &mut S,
+        varmap: &mut VarMap<V>,
+    ) -> i32 {
+        let repr = repr.unwrap_or_else(|| varmap.new_var());
+        let encoding = self.encoding;
+        let sums = self.constraint.at_most(varmap);
+
+        let mut encoder = PbEncoder::new(Direction::OutToIn, solver, varmap);
+        let mut clause: Vec<_> = sums
+            .iter()
+            .map(|(terms, bound)| encoder.overflow(terms, *bound, encoding))
+            .collect();
+        clause.push(Wire::Lit(repr));
+        encoder.clause(&clause);
+
+        repr
+    }
+
+    fn encode_constraint_equals_repr<S: Backend>(
+        self,
+        repr: Option<i32>,
+        solver: &mut S,
+        varmap: &mut VarMap<V>,
+    ) -> i32 {
+        let repr = repr.unwrap_or_else(|| varmap.new_var());
+        let encoding = self.encoding;
+        let sums = self.constraint.at_most(varmap);
+
+        let mut encoder = PbEncoder::new(Direction::Both, solver, varmap);
+        let mut clause: Vec<_> = sums
+            .iter()
+            .map(|(terms, bound)| encoder.overflow(terms, *bound, encoding))
+            .collect();
+
+        for &overflow in &clause {
+            encoder.clause(&[!overflow, Wire::Lit(-repr)]);
+        }
+        clause.push(Wire::Lit(repr));
+        encoder.clause(&clause);
+
+        repr
+    }
+}
+
+impl<I, L> Debug for PbLinear<I>
+where
+    I: Iterator<Item = (i64, L)> + Clone,
+    L: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let terms: Vec<_> = self.terms.clone().collect();
+
+        f.debug_struct("PbLinear")
+            .field("terms", &terms)
+            .field("cmp", &self.cmp)
+            .field("bound", &self.bound)
+            .finish()
+    }
+}
+
+/// Literal of an encoding, which can also be constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wire {
+    Const(bool),
+    Lit(i32),
+}
+
+impl ops::Not for Wire {
+    type Output = Wire;
+
+    fn not(self) -> Wire {
+        match self {
+            Wire::Const(value) => Wire::Const(!value),
+            Wire::Lit(lit) => Wire::Lit(-lit),
+        }
+    }
+}
+
+struct PbEncoder<'a, V, S> {
+    solver: &'a mut S,
+    varmap: &'a mut VarMap<V>,
+    dir: Direction,
+}
+
+impl<'a, V: SatVar, S: Backend> PbEncoder<'a, V, S> {
+    fn new(dir: Direction, solver: &'a mut S, varmap: &'a mut VarMap<V>) -> Self {
+        Self {
+            solver,
+            varmap,
+            dir,
+        }
+    }
+
+    fn new_wire(&mut self) -> Wire {
+        Wire::Lit(self.varmap.new_var())
+    }
+
+    /// Adds a clause, false wires are left out and true wires satisfy it.
+    fn clause(&mut self, wires: &[Wire]) {
+        if wires.contains(&Wire::Const(true)) {
+            return;
+        }
+
+        self.solver
+            .add_clause(wires.iter().filter_map(|wire| match wire {
+                Wire::Lit(lit) => Some(*lit),
+                Wire::Const(_) => None,
+            }));
+    }
+
+    /// Returns a wire which is true if the sum of `terms` is above `bound`.
+    ///
+    /// The direction of the encoder decides if a sum above `bound` implies the wire,
+    /// the wire implies such a sum or both.
+    fn overflow(
+        &mut self,
+        terms: &[(u64, i32)],
+        bound: i64,
+        encoding: PbEncoding,
+    ) -> Wire {
+        let total = terms.iter().map(|(weight, _)| weight).sum::<u64>();
+
+        if bound < 0 {
+            Wire::Const(true)
+        } else if bound as u64 >= total {
+            Wire::Const(false)
+        } else {
+            match encoding {
+                PbEncoding::Bdd => self.bdd(terms, bound as u64),
+                PbEncoding::GeneralizedTotalizer => {
+                    self.totalizer(terms, bound as u64)
+                }
+                PbEncoding::Adder => self.adder(terms, bound as u64),
+            }
+        }
+    }
+
+    fn totalizer(&mut self, terms: &[(u64, i32)], bound: u64) -> Wire {
+        let outputs = encode_generalized_totalizer(
+            terms,
+            bound + 1,
+            self.dir,
+            self.solver,
+            self.varmap,
+        );
+
+        match outputs.last() {
+            Some(&(sum, out)) if sum > bound => Wire::Lit(out),
+            _ => Wire::Const(false),
+        }
+    }
+
+    fn bdd(&mut self, terms: &[(u64, i32)], bound: u64) -> Wire {
+        let mut terms = terms.to_vec();
+        terms.sort_by_key(|&(weight, _)| Reverse(weight));
+
+        let mut suffix = vec![0; terms.len() + 1];
+        for i in (0..terms.len()).rev() {
+            suffix[i] = suffix[i + 1] + terms[i].0;
+        }
+
+        let mut nodes = vec![BTreeMap::new(); terms.len()];
+        let (_, _, root) =
+            self.bdd_node(&terms, &suffix, &mut nodes, 0, bound as i64);
+
+        !root
+    }
+
+    /// Returns the node which is true if the sum of `terms[i..]` is at most `k`,
+    /// together with the interval of bounds which have the same node.
+    ///
+    /// `nodes[i]` contains the encoded nodes of `terms[i..]` by the start of their
+    /// interval.
+    fn bdd_node(
+        &mut self,
+        terms: &[(u64, i32)],
+        suffix: &[u64],
+        nodes: &mut [BTreeMap<i64, (i64, Wire)>],
+        i: usize,
+        k: i64,
+    ) -> (i64, i64, Wire) {
+        if k < 0 {
+            return (i64::MIN, -1, Wire::Const(false));
+        }
+        if k as u64 >= suffix[i] {
+            return (suffix[i] as i64, i64::MAX, Wire::Const(true));
+        }
+        if let Some((&start, &(end, node))) = nodes[i].range(..=k).next_back() {
+            if k <= end {
+                return (start, end, node);
+            }
+        }
+
+        let (weight, lit) = terms[i];
+        let weight = weight as i64;
+
+        let (start0, end0, f) = self.bdd_node(terms, suffix, nodes, i + 1, k);
+        let (start1, end1, t) =
+            self.bdd_node(terms, suffix, nodes, i + 1, k - weight);
+
+        let start = start0.max(start1.saturating_add(weight));
+        let end = end0.min(end1.saturating_add(weight));
+
+        let node = if f == t {
+            f
+        } else {
+            // `t` implies `f`, so `node` is `if lit { t } else { f }`.
+            let node = self.new_wire();
+            let lit = Wire::Lit(lit);
+
+            if self.dir.in_to_out() {
+                self.clause(&[!node, f]);
+                self.clause(&[!node, !lit, t]);
+            }
+            if self.dir.out_to_in() {
+                self.clause(&[!t, node]);
+                self.clause(&[!f, lit, node]);
+            }
+
+            node
+        };
+
+        nodes[i].insert(start, (end, node));
+        (start, end, node)
+    }
+
+    fn adder(&mut self, terms: &[(u64, i32)], bound: u64) -> Wire {
+        // Wires to add up by the bit they count for.
+        let mut buckets: Vec<VecDeque<Wire>> = Vec::new();
+        for &(weight, lit) in terms {
+            for bit in (0..64).filter(|bit| weight >> bit & 1 == 1) {
+                if buckets.len() <= bit {
+                    buckets.resize(bit + 1, VecDeque::new());
+                }
+                buckets[bit].push_back(Wire::Lit(lit));
+            }
+        }
+
+        let mut sum = Vec::new();
+        let mut bit = 0;
+        while bit < buckets.len() {
+            while buckets[bit].len() > 1 {
+                let a = buckets[bit].pop_front().unwrap();
+                let b = buckets[bit].pop_front().unwrap();
+                let c = buckets[bit].pop_front();
+
+                let (s, carry) =
+                    self.full_adder(a, b, c.unwrap_or(Wire::Const(false)));
+                buckets[bit].push_back(s);
+
+                if buckets.len() <= bit + 1 {
+                    buckets.push(VecDeque::new());
+                }
+                buckets[bit + 1].push_back(carry);
+            }
+
+            sum.push(buckets[bit].pop_front().unwrap_or(Wire::Const(false)));
+            bit += 1;
+        }
+
+        // `sum` is above `bound` if it is above it in the lowest `i` bits and the bit
+        // `i` is at least the one of `bound`.
+        let mut above = Wire::Const(false);
+        for (i, &s) in sum.iter().enumerate() {
+            above = if bound.checked_shr(i as u32).unwrap_or(0) & 1 == 1 {
+                self.and(s, above)
+            } else {
+                self.or(s, above)
+            };
+        }
+
+        above
+    }
+
+    /// Returns the sum and the carry of three bits.
+    fn full_adder(&mut self, a: Wire, b: Wire, c: Wire) -> (Wire, Wire) {
+        let sum = self.new_wire();
+        let carry = self.new_wire();
+
+        for mask in 0..8 {
+            let wires: Vec<_> = [a, b, c]
+                .iter()
+                .enumerate()
+                .map(|(i, &w)| if mask >> i & 1 == 1 { !w } else { w })
+                .collect();
+            let parity = (mask as u32).count_ones() % 2 == 1;
+
+            let s = if parity { sum } else { !sum };
+            self.clause(&[wires[0], wires[1], wires[2], s]);
+        }
+
+        for &(x, y) in &[(a, b), (a, c), (b, c)] {
+            self.clause(&[!x, !y, carry]);
+            self.clause(&[x, y, !carry]);
+        }
+
+        (sum, carry)
+    }
+
+    fn and(&mut self, a: Wire, b: Wire) -> Wire {
+        match (a, b) {
+            (Wire::Const(false), _) | (_, Wire::Const(false)) => Wire::Const(false),
+            (Wire::Const(true), wire) | (wire, Wire::Const(true)) => wire,
+            _ => {
+                let out = self.new_wire();
+                if self.dir.in_to_out() {
+                    self.clause(&[!a, !b, out]);
+                }
+                if self.dir.out_to_in() {
+                    self.clause(&[!out, a]);
+                    self.clause(&[!out, b]);
+                }
+                out
+            }
+        }
+    }
+
+    fn or(&mut self, a: Wire, b: Wire) -> Wire {
+        match (a, b) {
+            (Wire::Const(true), _) | (_, Wire::Const(true)) => Wire::Const(true),
+            (Wire::Const(false), wire) | (wire, Wire::Const(false)) => wire,
+            _ => {
+                let out = self.new_wire();
+                if self.dir.in_to_out() {
+                    self.clause(&[!a, out]);
+                    self.clause(&[!b, out]);
+                }
+                if self.dir.out_to_in() {
+                    self.clause(&[!out, a, b]);
+                }
+                out
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constraints::{
+            test_util::{
+                constraint_equals_repr_tester, constraint_implies_repr_tester,
+                retry_until_unsat,
+            },
+            Equal,
+        },
+        CadicalEncoder, Model,
+    };
+
+    const WEIGHTS: [i64; 6] = [3, -2, 4, 1, -1, 2];
+
+    const ENCODINGS: [PbEncoding; 3] = [
+        PbEncoding::Bdd,
+        PbEncoding::GeneralizedTotalizer,
+        PbEncoding::Adder,
+    ];
+
+    const COMPARISONS: [(Comparison, &[i64]); 3] = [
+        (Comparison::Le, &[-4, -3, 0, 3, 5, 9]),
+        (Comparison::Ge, &[-2, 0, 3, 5, 10, 11]),
+        (Comparison::Eq, &[-4, -3, 0, 3, 10, 11]),
+    ];
+
+    fn holds(cmp: Comparison, sum: i64, bound: i64) -> bool {
+        match cmp {
+            Comparison::Le => sum <= bound,
+            Comparison::Ge => sum >= bound,
+            Comparison::Eq => sum == bound,
+        }
+    }
+
+    fn sum(model: &Model<u32>) -> i64 {
+        (0..WEIGHTS.len() as u32)
+            .filter(|&v| model[v])
+            .map(|v| WEIGHTS[v as usize])
+            .sum()
+    }
+
+    fn expected(cmp: Comparison, bound: i64) -> usize {
+        (0..1 << WEIGHTS.len())
+            .filter(|set: &u32| {
+                let sum = (0..WEIGHTS.len())
+                    .filter(|i| set & (1 << i) != 0)
+                    .map(|i| WEIGHTS[i])
+                    .sum();
+                holds(cmp, sum, bound)
+            })
+            .count()
+    }
+
+    /// Encoder in which every variable of the terms occurs, even if the constraint
+    /// does not use it.
+    fn encoder() -> CadicalEncoder<u32> {
+        let mut encoder = CadicalEncoder::new();
+        let n = WEIGHTS.len() as u32;
+        for v in 0..n {
+            encoder.add_constraint(Equal(vec![v, v + n].into_iter()));
+        }
+        encoder
+    }
+
+    fn constraint(
+        cmp: Comparison,
+        bound: i64,
+    ) -> PbLinear<impl Iterator<Item = (i64, u32)> + Clone> {
+        PbLinear {
+            terms: WEIGHTS.iter().copied().zip(0..),
+            cmp,
+            bound,
+        }
+    }
+
+    #[test]
+    fn normal_pb_linear() {
+        for &encoding in &ENCODINGS {
+            for &(cmp, bounds) in &COMPARISONS {
+                for &bound in bounds {
+                    let mut encoder = encoder();
+                    encoder.add_constraint(
+                        constraint(cmp, bound).with_encoding(encoding),
+                    );
+
+                    let res = retry_until_unsat(&mut encoder, |model| {
+                        assert!(holds(cmp, sum(model), bound))
+                    });
+                    assert_eq!(
+                        res,
+                        expected(cmp, bound),
+                        "{:?} {:?} {}",
+                        encoding,
+                        cmp,
+                        bound
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pb_linear_implies_repr() {
+        for &encoding in &ENCODINGS {
+            for &(cmp, bounds) in &COMPARISONS {
+                for &bound in bounds {
+                    let mut encoder = encoder();
+                    let repr = constraint(cmp, bound)
+                        .with_encoding(encoding)
+                        .encode_constraint_implies_repr(
+                            None,
+                            &mut encoder.backend,
+                            &mut encoder.varmap,
+                        );
+
+                    let res = constraint_implies_repr_tester(
+                        &mut encoder,
+                        repr,
+                        |model| holds(cmp, sum(model), bound),
+                    );
+                    assert_eq!(res.correct, expected(cmp, bound));
+                    assert_eq!(res.total(), 1 << WEIGHTS.len());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pb_linear_equals_repr() {
+        for &encoding in &ENCODINGS {
+            for &(cmp, bounds) in &COMPARISONS {
+                for &bound in bounds {
+                    let mut encoder = encoder();
+                    let repr = constraint(cmp, bound)
+                        .with_encoding(encoding)
+                        .encode_constraint_equals_repr(
+                            None,
+                            &mut encoder.backend,
+                            &mut encoder.varmap,
+                        );
+
+                    let res =
+                        constraint_equals_repr_tester(&mut encoder, repr, |model| {
+                            holds(cmp, sum(model), bound)
+                        });
+                    assert_eq!(res.correct, expected(cmp, bound));
+                    assert_eq!(res.total(), 1 << WEIGHTS.len());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{
+    circuit::{Circuit, Direction},
+    clause, Backend, SatVar, VarMap,
+};
+
+/// Wire of a sorting network, `None` is constantly false.
+type Wire = Option<i32>;
+
+/// Encodes a Batcher odd-even merge sorting network over `lits`.
+///
+/// Returns the sorted outputs, the output at index `i` stands for at least `i + 1`
+/// true inputs. Outputs which are always false are `None`.
+pub(crate) fn encode_sorting_network<V, S>(
+    lits: &[i32],
+    dir: Direction,
+    solver: &mut S,
+    varmap: &mut VarMap<V>,
+) -> Vec<Wire>
+where
+    V: SatVar,
+    S: Backend,
+{
+    let mut network = Network::new(dir, solver, varmap);
+
+    let mut out = network.sort(&pad(lits, lits.len().next_power_of_two()));
+    out.truncate(lits.len());
+    out
+}
+
+/// Encodes the cardinality network of Asín et al. over `lits`, which sorts only the
+/// first `k` outputs.
+///
+/// Returns at least `k` outputs if there are enough inputs, otherwise like
+/// [`encode_sorting_network`].
+pub(crate) fn encode_cardinality_network<V, S>(
+    lits: &[i32],
+    k: usize,
+    dir: Direction,
+    solver: &mut S,
+    varmap: &mut VarMap<V>,
+) -> Vec<Wire>
+where
+    V: SatVar,
+    S: Backend,
+{
+    assert!(k > 0);
+
+    let block = k.next_power_of_two();
+    if block >= lits.len() {
+        return encode_sorting_network(lits, dir, solver, varmap);
+    }
+
+    let mut network = Network::new(dir, solver, varmap);
+    let wires = pad(lits, lits.len().div_ceil(block) * block);
+
+    let mut blocks = wires.chunks(block);
+    let mut out = network.sort(blocks.next().unwrap());
+
+    for wires in blocks {
+        let sorted = network.sort(wires);
+        out = network.simplified_merge(&out, &sorted);
+        out.truncate(block);
+    }
+
+    out
+}
+
+/// The literals as wires, padded with false wires to `len`.
+fn pad(lits: &[i32], len: usize) -> Vec<Wire> {
+    let mut wires: Vec<_> = lits.iter().copied().map(Some).collect();
+    wires.resize(len, None);
+    wires
+}
+
+/// Every second wire starting at `start`.
+fn step(wires: &[Wire], start: usize) -> Vec<Wire> {
+    wires.iter().skip(start).step_by(2).copied().collect()
+}
+
+struct Network<'a, V, S> {
+    circuit: Circuit<'a, S>,
+    varmap: &'a mut VarMap<V>,
+}
+
+impl<'a, V: SatVar, S: Backend> Network<'a, V, S> {
+    fn new(dir: Direction, solver: &'a mut S, varmap: &'a mut VarMap<V>) -> Self {
+        Self {
+            circuit: Circuit::new(solver, dir),
+            varmap,
+        }
+    }
+
+    /// Sorts two wires, returns the maximum and the minimum.
+    fn comparator(&mut self, a: Wire, b: Wire) -> (Wire, Wire) {
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                let max = self.varmap.new_var();
+                let min = self.varmap.new_var();
+
+                self.circuit.or_gate(clause![a, b], max);
+                self.circuit.and_gate(clause![a, b], min);
+
+                (Some(max), Some(min))
+            }
+            (wire, None) | (None, wire) => (wire, None),
+        }
+    }
+
+    /// Sorts `wires`, their number has to be a power of two.
+    fn sort(&mut self, wires: &[Wire]) -> Vec<Wire> {
+        if wires.len() <= 1 {
+            return wires.to_vec();
+        }
+
+        let (left, right) = wires.split_at(wires.len() / 2);
+        let left = self.sort(left);
+        let right = self.sort(right);
+
+        self.merge(&left, &right)
+    }
+
+    /// Merges two sorted sequences of the same length, which is a power of two.
+    fn merge(&mut self, a: &[Wire], b: &[Wire]) -> Vec<Wire> {
+        if a.len() == 1 {
+            let (max, min) = self.comparator(a[0], b[0]);
+            return vec![max, min];
+        }
+
+        let odd = self.merge(&step(a, 0), &step(b, 0));
+        let even = self.merge(&step(a, 1), &step(b, 1));
+
+        let mut out = vec![odd[0]];
+        for i in 1..odd.len() {
+            let (max, min) = self.comparator(odd[i], even[i - 1]);
+            out.push(max);
+            out.push(min);
+        }
+        out.push(*even.last().unwrap());
+
+        out
+    }
+
+    /// Like `merge`, but only the first `a.len() + 1` outputs are returned and
+    /// sorted.
+    fn simplified_merge(&mut self, a: &[Wire], b: &[Wire]) -> Vec<Wire> {
+        if a.len() == 1 {
+            let (max, min) = self.comparator(a[0], b[0]);
+            return vec![max, min];
+        }
+
+        let odd = self.simplified_merge(&step(a, 0), &step(b, 0));
+        let even = self.simplified_merge(&step(a, 1), &step(b, 1));
+
+        let mut out = vec![odd[0]];
+        for i in 1..=a.len() / 2 {
+            let (max, min) = self.comparator(odd[i], even[i - 1]);
+            out.push(max);
+            out.push(min);
+        }
+
+        out
+    }
+}
@@ -88,7 +88,7 @@ pub fn constraint_implies_repr_tester<V: SatVar + Ord>(
         let clause: Vec<_> =
             model.vars().map(|l| varmap.get_var(!l).unwrap()).collect();
 
-        encoder.backend.add_clause(clause.into_iter());
+        encoder.backend.add_clause(clause);
     }
 
     ConstraintTestResult {
@@ -147,7 +147,7 @@ pub fn constraint_equals_repr_tester<V: SatVar + Ord>(
         let clause: Vec<_> =
             model.vars().map(|l| varmap.get_var(!l).unwrap()).collect();
 
-        encoder.backend.add_clause(clause.into_iter());
+        encoder.backend.add_clause(clause);
     }
 
     ConstraintTestResult {
@@ -0,0 +1,356 @@
+use crate::{circuit::Direction, clause, Backend, SatVar, VarMap};
+
+/// Encodes a generalized totalizer over the weighted literals `terms`.
+///
+/// Returns the output literals together with the sum they stand for, ordered by
+/// the sum. If the weights of the true literals add up to `s`, the output of `s`
+/// is true. Sums above `clip` are merged into a single output of `clip`, so only
+/// the outputs up to `clip` are exact.
+///
+/// With `Direction::InToOut` forbidding all outputs above `b` encodes that the
+/// weighted sum is at most `b`. With `Direction::OutToIn` a true output implies
+/// that the sum is at least the one of the output, the true outputs are a prefix.
+pub(crate) fn encode_generalized_totalizer<V, S>(
+    terms: &[(u64, i32)],
+    clip: u64,
+    dir: Direction,
+    solver: &mut S,
+    varmap: &mut VarMap<V>,
+) -> Vec<(u64, i32)>
+where
+    V: SatVar,
+    S: Backend,
+{
+    let terms: Vec<_> = terms
+        .iter()
+        .filter(|(w, _)| *w > 0)
+        .map(|&(w, lit)| (w.min(clip), lit))
+        .collect();
+
+    match terms.len() {
+        0 => Vec::new(),
+        1 => terms,
+        n => {
+            let (left, right) = terms.split_at(n / 2);
+            let left = encode_generalized_totalizer(left, clip, dir, solver, varmap);
+            let right =
+                encode_generalized_totalizer(right, clip, dir, solver, varmap);
+
+            merge(&left, &right, clip, dir, solver, varmap)
+        }
+    }
+}
+
+/// Merges the outputs of two totalizer nodes.
+fn merge<V, S>(
+    left: &[(u64, i32)],
+    right: &[(u64, i32)],
+    clip: u64,
+    dir: Direction,
+    solver: &mut S,
+    varmap: &mut VarMap<V>,
+) -> Vec<(u64, i32)>
+where
+    V: SatVar,
+    S: Backend,
+{
+    let mut sums: Vec<_> = left
+        .iter()
+        .chain(right)
+        .map(|&(s, _)| s)
+        .chain(
+            left.iter()
+                .flat_map(|&(a, _)| right.iter().map(move |&(b, _)| a + b)),
+        )
+        .map(|s| s.min(clip))
+        .collect();
+    sums.sort_unstable();
+    sums.dedup();
+
+    let outputs: Vec<_> = sums.into_iter().map(|s| (s, varmap.new_var())).collect();
+    let output = |sum: u64| {
+        let i = outputs.binary_search_by_key(&sum.min(clip), |&(s, _)| s);
+        outputs[i.unwrap()].1
+    };
+
+    if dir.in_to_out() {
+        for &(s, lit) in left.iter().chain(right) {
+            solver.add_clause(clause![-lit, output(s)]);
+        }
+        for &(a, l) in left {
+            for &(b, r) in right {
+                solver.add_clause(clause![-l, -r, output(a + b)]);
+            }
+        }
+    }
+
+    if dir.out_to_in() {
+        for pair in outputs.windows(2) {
+            solver.add_clause(clause![-pair[1].1, pair[0].1]);
+        }
+
+        // If only the outputs before `i` and `j` are true, the sum is at most `max`.
+        for i in 0..=left.len() {
+            for j in 0..=right.len() {
+                let max = [(left, i), (right, j)]
+                    .iter()
+                    .map(|&(node, i)| i.checked_sub(1).map_or(0, |i| node[i].0))
+                    .sum::<u64>();
+
+                if let Some(&(_, out)) = outputs.iter().find(|&&(s, _)| s > max) {
+                    let lits = left.get(i).into_iter().chain(right.get(j));
+                    solver.add_clause(lits.map(|&(_, l)| l).chain(clause![-out]));
+                }
+            }
+        }
+    }
+
+    outputs
+}
+
+/// Encodes a totalizer over `lits` whose nodes count up to `bound` true inputs.
+///
+/// Returns `min(lits.len(), bound)` outputs, the output at index `i` stands for at
+/// least `i + 1` true inputs. With `bound >= lits.len()` this is the plain
+/// totalizer, otherwise the k-simplified one.
+pub(crate) fn encode_totalizer<V, S>(
+    lits: &[i32],
+    bound: usize,
+    dir: Direction,
+    solver: &mut S,
+    varmap: &mut VarMap<V>,
+) -> Vec<i32>
+where
+    V: SatVar,
+    S: Backend,
+{
+    assert!(bound > 0);
+
+    match lits.len() {
+        0 => panic!("No variables to encode"),
+        1 => lits.to_vec(),
+        n => {
+            let left = encode_totalizer(&lits[..n / 2], bound, dir, solver, varmap);
+            let right = encode_totalizer(&lits[n / 2..], bound, dir, solver, varmap);
+
+            let outputs: Vec<_> =
+                (0..n.min(bound)).map(|_| varmap.new_var()).collect();
+
+            // `left[a - 1]` is true if at least `a` inputs on the left are true,
+            // `left[a]` is false if at most `a` are true.
+            for a in 0..=left.len() {
+                for b in 0..=right.len() {
+                    let sum = a + b;
+
+                    if dir.in_to_out() && sum > 0 && sum <= outputs.len() {
+                        let lits = left
+                            .get(a.wrapping_sub(1))
+                            .into_iter()
+                            .chain(right.get(b.wrapping_sub(1)))
+                            .map(|&lit| -lit);
+                        solver.add_clause(lits.chain(clause![outputs[sum - 1]]));
+                    }
+
+                    if dir.out_to_in() && sum < outputs.len() {
+                        let lits = left.get(a).into_iter().chain(right.get(b));
+                        solver
+                            .add_clause(lits.copied().chain(clause![-outputs[sum]]));
+                    }
+                }
+            }
+
+            outputs
+        }
+    }
+}
+
+/// Totalizer whose outputs can be extended after it was encoded.
+///
+/// `outputs()[i]` is true if at least `i + 1` inputs are true. Like the generalized
+/// totalizer only the direction from the inputs to the outputs is encoded.
+#[derive(Debug, Clone)]
+pub(crate) struct Totalizer {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    /// Number of inputs below this node.
+    size: usize,
+    /// Child nodes, `None` for leaves.
+    children: Option<(usize, usize)>,
+    outputs: Vec<i32>,
+}
+
+impl Totalizer {
+    /// Creates a totalizer over `lits` without any outputs.
+    pub fn new(lits: &[i32]) -> Self {
+        assert!(!lits.is_empty(), "No variables to encode");
+
+        let mut totalizer = Self { nodes: Vec::new() };
+        totalizer.build(lits);
+        totalizer
+    }
+
+    /// Adds the nodes for `lits` and returns the index of their root.
+    fn build(&mut self, lits: &[i32]) -> usize {
+        let node = match lits {
+            [lit] => Node {
+                size: 1,
+                children: None,
+                outputs: vec![*lit],
+            },
+            _ => {
+                let left = self.build(&lits[..lits.len() / 2]);
+                let right = self.build(&lits[lits.len() / 2..]);
+                Node {
+                    size: lits.len(),
+                    children: Some((left, right)),
+                    outputs: Vec::new(),
+                }
+            }
+        };
+
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// Number of inputs.
+    pub fn len(&self) -> usize {
+        self.nodes.last().unwrap().size
+    }
+
+    /// The encoded outputs, the output at index `i` stands for at least `i + 1`
+    /// true inputs.
+    pub fn outputs(&self) -> &[i32] {
+        &self.nodes.last().unwrap().outputs
+    }
+
+    /// Encodes the outputs up to at least `bound` true inputs.
+    pub fn extend<V, S>(
+        &mut self,
+        bound: usize,
+        solver: &mut S,
+        varmap: &mut VarMap<V>,
+    ) where
+        V: SatVar,
+        S: Backend,
+    {
+        self.extend_node(self.nodes.len() - 1, bound, solver, varmap);
+    }
+
+    fn extend_node<V, S>(
+        &mut self,
+        node: usize,
+        bound: usize,
+        solver: &mut S,
+        varmap: &mut VarMap<V>,
+    ) where
+        V: SatVar,
+        S: Backend,
+    {
+        let bound = bound.min(self.nodes[node].size);
+        let (left, right) = match self.nodes[node].children {
+            Some(children) if self.nodes[node].outputs.len() < bound => children,
+            _ => return,
+        };
+
+        self.extend_node(left, bound, solver, varmap);
+        self.extend_node(right, bound, solver, varmap);
+
+        let encoded = self.nodes[node].outputs.len();
+        for sum in (encoded + 1)..=bound {
+            let out = varmap.new_var();
+            let (l, r) = (&self.nodes[left].outputs, &self.nodes[right].outputs);
+
+            // At least `a` true inputs on the left and `sum - a` on the right.
+            for a in 0..=sum.min(l.len()) {
+                let b = sum - a;
+                if b > r.len() {
+                    continue;
+                }
+
+                let lits = l
+                    .get(a.wrapping_sub(1))
+                    .into_iter()
+                    .chain(r.get(b.wrapping_sub(1)));
+                solver.add_clause(lits.map(|&lit| -lit).chain(clause![out]));
+            }
+
+            self.nodes[node].outputs.push(out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::CdclEncoder, Lit, Projection, VarType};
+
+    #[test]
+    fn totalizer_bounds_weighted_sum() {
+        let weights = [3, 1, 4, 1, 5];
+
+        for bound in 0..=14 {
+            let mut encoder = CdclEncoder::<u32>::new();
+            let terms: Vec<_> = weights
+                .iter()
+                .enumerate()
+                .map(|(i, &w)| (w, encoder.varmap.add_var(Lit::Pos(i as u32))))
+                .collect();
+
+            let outputs = encode_generalized_totalizer(
+                &terms,
+                bound + 1,
+                Direction::InToOut,
+                &mut encoder.backend,
+                &mut encoder.varmap,
+            );
+            for &(s, o) in &outputs {
+                if s > bound {
+                    encoder.backend.add_clause(clause![-o]);
+                }
+            }
+
+            let expected = (0..1u32 << weights.len())
+                .filter(|set| {
+                    let sum: u64 = (0..weights.len())
+                        .filter(|i| set & (1 << i) != 0)
+                        .map(|i| weights[i])
+                        .sum();
+                    sum <= bound
+                })
+                .count();
+
+            let count = encoder.models(Projection::all()).count();
+            assert_eq!(count, expected, "bound {}", bound);
+        }
+    }
+
+    #[test]
+    fn incremental_totalizer() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        let lits: Vec<_> = (0..7)
+            .map(|i| encoder.varmap.add_var(Lit::Pos(i)))
+            .collect();
+
+        let mut totalizer = Totalizer::new(&lits);
+        assert_eq!(totalizer.len(), 7);
+
+        for bound in 1..=7 {
+            totalizer.extend(bound, &mut encoder.backend, &mut encoder.varmap);
+            let out = totalizer.outputs()[bound - 1];
+
+            // Models with less than `bound` true inputs.
+            let expected: usize = (0..bound).map(|k| binomial(7, k)).sum();
+            encoder.push();
+            encoder.add_constraint(VarType::Unnamed(-out));
+            assert_eq!(encoder.models(Projection::all()).count(), expected);
+            encoder.pop();
+        }
+    }
+
+    fn binomial(n: usize, k: usize) -> usize {
+        (0..k).fold(1, |acc, i| acc * (n - i) / (i + 1))
+    }
+}
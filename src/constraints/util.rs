@@ -44,7 +44,7 @@ mod tests {
     };
     #[test]
     fn repr_implies_constraint() {
-        let lits = (1..=5).map(|i| Lit::Pos(i));
+        let lits = (1..=5).map(Lit::Pos);
         let k = 2;
         let constraint = AtMostK { k, lits };
 
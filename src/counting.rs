@@ -0,0 +1,338 @@
+use ahash::{HashMap, HashMapExt, HashSet};
+
+use crate::{
+    backend::Cdcl, Backend, ClauseDatabase, Encoder, Projection, SatVar,
+    SolveResult, Solver,
+};
+
+type Clause = Vec<i32>;
+
+/// Exact projected model counter using component decomposition and caching.
+///
+/// Components without projected variables are only checked for satisfiability.
+struct ModelCounter<'a> {
+    projected: &'a HashSet<i32>,
+    cache: HashMap<Vec<Clause>, u128>,
+}
+
+impl ModelCounter<'_> {
+    /// Counts the assignments of the projected variables occurring in `clauses`
+    /// which can be extended to a model.
+    fn count(&mut self, clauses: Vec<Clause>) -> u128 {
+        let before = self.projected_vars(&clauses);
+
+        let (clauses, assigned) = match propagate(clauses) {
+            Some(result) => result,
+            None => return 0,
+        };
+
+        let after = self.projected_vars(&clauses);
+        let assigned = assigned
+            .iter()
+            .filter(|v| self.projected.contains(v))
+            .count();
+
+        // Projected variables whose clauses are all satisfied can take any value.
+        let mut count = pow2(before.len() - after.len() - assigned);
+
+        for component in components(clauses) {
+            count = mul(count, self.count_component(component));
+            if count == 0 {
+                break;
+            }
+        }
+        count
+    }
+
+    fn count_component(&mut self, mut clauses: Vec<Clause>) -> u128 {
+        // Variables are allocated in encoding order, so branching on the oldest one
+        // leaves residual formulas which are likely to be seen again.
+        let var = clauses
+            .iter()
+            .flatten()
+            .map(|l| l.abs())
+            .filter(|v| self.projected.contains(v))
+            .min();
+
+        let var = match var {
+            Some(var) => var,
+            None => return is_satisfiable(&clauses) as u128,
+        };
+
+        for clause in &mut clauses {
+            clause.sort_unstable();
+        }
+        clauses.sort_unstable();
+
+        if let Some(&count) = self.cache.get(&clauses) {
+            return count;
+        }
+
+        let mut count = 0;
+        for &lit in &[var, -var] {
+            let mut branch = clauses.clone();
+            branch.push(vec![lit]);
+            count = add(count, self.count(branch));
+        }
+
+        self.cache.insert(clauses, count);
+        count
+    }
+
+    fn projected_vars(&self, clauses: &[Clause]) -> HashSet<i32> {
+        clauses
+            .iter()
+            .flatten()
+            .map(|l| l.abs())
+            .filter(|v| self.projected.contains(v))
+            .collect()
+    }
+}
+
+/// Applies unit propagation until fixpoint.
+/// Returns the simplified clauses and the assigned variables or `None` on a
+/// conflict.
+fn propagate(clauses: Vec<Clause>) -> Option<(Vec<Clause>, Vec<i32>)> {
+    let mut occurrences = HashMap::<i32, Vec<usize>>::new();
+    let mut queue = Vec::new();
+
+    for (i, clause) in clauses.iter().enumerate() {
+        match clause.len() {
+            0 => return None,
+            1 => queue.push(clause[0]),
+            _ => {}
+        }
+        for lit in clause {
+            occurrences.entry(lit.abs()).or_default().push(i);
+        }
+    }
+
+    let mut assignment = HashMap::<i32, bool>::new();
+    let mut satisfied = vec![false; clauses.len()];
+    let mut open: Vec<_> = clauses.iter().map(|c| c.len()).collect();
+
+    while let Some(lit) = queue.pop() {
+        match assignment.insert(lit.abs(), lit > 0) {
+            Some(value) if value == (lit > 0) => continue,
+            Some(_) => return None,
+            None => {}
+        }
+
+        for &i in occurrences.get(&lit.abs()).into_iter().flatten() {
+            if satisfied[i] {
+                continue;
+            }
+            if clauses[i].contains(&lit) {
+                satisfied[i] = true;
+                continue;
+            }
+
+            open[i] -= 1;
+            match open[i] {
+                0 => return None,
+                1 => {
+                    let unit = clauses[i]
+                        .iter()
+                        .find(|l| !assignment.contains_key(&l.abs()))
+                        .copied();
+                    // All literals may be assigned if `lit` occurs twice.
+                    match unit {
+                        Some(unit) => queue.push(unit),
+                        None => return None,
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let clauses = clauses
+        .into_iter()
+        .zip(satisfied)
+        .filter(|(_, satisfied)| !satisfied)
+        .map(|(c, _)| {
+            c.into_iter()
+                .filter(|l| !assignment.contains_key(&l.abs()))
+                .collect()
+        })
+        .collect();
+
+    Some((clauses, assignment.into_keys().collect()))
+}
+
+/// Splits `clauses` into groups which don't share any variables.
+fn components(clauses: Vec<Clause>) -> Vec<Vec<Clause>> {
+    let mut index = HashMap::<i32, usize>::new();
+    let mut parent = Vec::new();
+
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for clause in &clauses {
+        let mut roots = clause.iter().map(|l| {
+            let next = parent.len();
+            let i = *index.entry(l.abs()).or_insert(next);
+            if i == next {
+                parent.push(i);
+            }
+            i
+        });
+
+        let first = roots.next().unwrap();
+        let rest: Vec<_> = roots.collect();
+        for i in rest {
+            let (a, b) = (find(&mut parent, first), find(&mut parent, i));
+            parent[a] = b;
+        }
+    }
+
+    let mut groups = HashMap::<usize, Vec<Clause>>::new();
+    for clause in clauses {
+        let root = find(&mut parent, index[&clause[0].abs()]);
+        groups.entry(root).or_default().push(clause);
+    }
+
+    groups.into_values().collect()
+}
+
+fn is_satisfiable(clauses: &[Clause]) -> bool {
+    let mut solver = Cdcl::new();
+    for clause in clauses {
+        solver.add_clause(clause.iter().copied());
+    }
+
+    solver.solve() == SolveResult::Sat
+}
+
+fn pow2(n: usize) -> u128 {
+    assert!(n < 128, "model count overflows u128");
+    1 << n
+}
+
+fn mul(a: u128, b: u128) -> u128 {
+    a.checked_mul(b).expect("model count overflows u128")
+}
+
+fn add(a: u128, b: u128) -> u128 {
+    a.checked_add(b).expect("model count overflows u128")
+}
+
+impl<V: SatVar, S: ClauseDatabase> Encoder<V, S> {
+    /// Counts the distinct assignments of the projected variables which can be
+    /// extended to a model, without enumerating them.
+    ///
+    /// Unconstrained projected variables double the count.
+    ///
+    /// Wrap solvers which don't implement [`ClauseDatabase`] in a
+    /// [`Recorder`](crate::backend::Recorder).
+    ///
+    /// # Panics
+    /// Panics if the count doesn't fit into `u128`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::ExactlyK, Projection};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// encoder.add_constraint(ExactlyK { k: 10, lits: 0..30 });
+    ///
+    /// assert_eq!(encoder.count_models(Projection::all()), 30_045_015);
+    /// assert_eq!(encoder.count_models(Projection::vars(0..10)), 1 << 10);
+    /// # }
+    /// ```
+    pub fn count_models(&mut self, projection: Projection<V>) -> u128 {
+        let projected: HashSet<i32> =
+            projection.resolve(&self.varmap).into_iter().collect();
+
+        let mut clauses = self.backend.clauses();
+        clauses.extend(self.activation.enabled.iter().map(|&l| vec![l]));
+
+        let occurring: HashSet<i32> =
+            clauses.iter().flatten().map(|l| l.abs()).collect();
+        let missing = projected.iter().filter(|v| !occurring.contains(v)).count();
+
+        let mut counter = ModelCounter {
+            projected: &projected,
+            cache: HashMap::new(),
+        };
+
+        match counter.count(clauses) {
+            0 => 0,
+            count => mul(count, pow2(missing)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::CdclEncoder,
+        constraints::{AtMostK, ExactlyK, Or},
+        Lit,
+    };
+
+    fn binomial(n: u128, k: u128) -> u128 {
+        (0..k).fold(1, |acc, i| acc * (n - i) / (i + 1))
+    }
+
+    #[test]
+    fn count_cardinality() {
+        for k in 0..=8 {
+            let mut encoder = CdclEncoder::<u32>::new();
+            encoder.add_constraint(ExactlyK { k, lits: 0..24 });
+
+            assert_eq!(
+                encoder.count_models(Projection::all()),
+                binomial(24, k as u128)
+            );
+        }
+    }
+
+    #[test]
+    fn count_matches_enumeration() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(AtMostK { k: 3, lits: 0..8 });
+        encoder.add_constraint(Or(
+            vec![Lit::Pos(1), Lit::Neg(5), Lit::Pos(9)].into_iter()
+        ));
+        encoder.add_constraint(ExactlyK { k: 2, lits: 6..12 });
+
+        let projections: [fn() -> Projection<u32>; 3] = [
+            || Projection::all(),
+            || Projection::vars(vec![0, 3, 5, 9, 11]),
+            || Projection::filter(|&v| v % 3 == 0),
+        ];
+
+        for projection in projections.iter() {
+            let enumerated = encoder.models(projection()).count() as u128;
+            assert_eq!(encoder.count_models(projection()), enumerated);
+        }
+    }
+
+    #[test]
+    fn count_respects_guards() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(AtMostK { k: 1, lits: 0..4 });
+        assert_eq!(encoder.count_models(Projection::all()), 5);
+
+        let guard = encoder.add_guarded_constraint(Lit::Pos(0));
+        assert_eq!(encoder.count_models(Projection::all()), 1);
+
+        encoder.add_constraint(Lit::Pos(1));
+        assert_eq!(encoder.count_models(Projection::all()), 0);
+
+        encoder.disable_guard(&guard);
+        assert_eq!(encoder.count_models(Projection::all()), 1);
+
+        // Known variables without clauses are free, unknown ones are skipped.
+        encoder.varmap.add_var(Lit::Pos(7));
+        assert_eq!(encoder.count_models(Projection::vars(vec![7, 8])), 2);
+    }
+}
@@ -0,0 +1,228 @@
+use std::iter;
+
+use crate::{Encoder, IncrementalSolver, Lit, Model, SatVar, SolveResult, VarMap};
+
+/// Selects the variables a model is projected on.
+///
+/// Two models are considered equal if they agree on all projected variables.
+pub struct Projection<V> {
+    kind: ProjectionKind<V>,
+}
+
+enum ProjectionKind<V> {
+    All,
+    Vars(Vec<V>),
+    Filter(Box<dyn Fn(&V) -> bool>),
+}
+
+impl<V: SatVar> Projection<V> {
+    /// Project on all named variables.
+    pub fn all() -> Self {
+        Self {
+            kind: ProjectionKind::All,
+        }
+    }
+
+    /// Project on the given variables.
+    ///
+    /// Variables which don't occur in the encoder are skipped, they can't
+    /// distinguish two models.
+    pub fn vars<I: IntoIterator<Item = V>>(vars: I) -> Self {
+        Self {
+            kind: ProjectionKind::Vars(vars.into_iter().collect()),
+        }
+    }
+
+    /// Project on all named variables for which `pred` returns true.
+    pub fn filter<F>(pred: F) -> Self
+    where
+        F: Fn(&V) -> bool + 'static,
+    {
+        Self {
+            kind: ProjectionKind::Filter(Box::new(pred)),
+        }
+    }
+
+    /// Returns the integer SAT variables of the projection ordered by their id.
+    pub(crate) fn resolve(&self, varmap: &VarMap<V>) -> Vec<i32> {
+        let mut ids: Vec<_> = match &self.kind {
+            ProjectionKind::All => {
+                varmap.named_vars().into_iter().map(|(id, _)| id).collect()
+            }
+            ProjectionKind::Vars(vars) => vars
+                .iter()
+                .filter_map(|v| varmap.get_var(Lit::Pos(v.clone())))
+                .collect(),
+            ProjectionKind::Filter(pred) => varmap
+                .named_vars()
+                .into_iter()
+                .filter(|(_, v)| pred(v))
+                .map(|(id, _)| id)
+                .collect(),
+        };
+
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+/// Iterator over the models of an [`Encoder`], created by [`Encoder::models`].
+///
+/// Each model differs from all previous ones in at least one projected variable.
+/// The blocking clauses are disabled once the iterator is dropped.
+pub struct Models<'a, V, S: IncrementalSolver> {
+    encoder: &'a mut Encoder<V, S>,
+    projection: Vec<i32>,
+    guard: i32,
+    limit: Option<usize>,
+    found: usize,
+    result: Option<SolveResult>,
+}
+
+impl<'a, V: SatVar, S: IncrementalSolver> Models<'a, V, S> {
+    /// Stop after `limit` models.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns true if all models were enumerated.
+    /// This is false if the limit was reached or the solver failed.
+    pub fn is_complete(&self) -> bool {
+        matches!(self.result, Some(SolveResult::Unsat(_)))
+    }
+
+    /// Number of models returned so far.
+    pub fn found(&self) -> usize {
+        self.found
+    }
+}
+
+impl<'a, V: SatVar, S: IncrementalSolver> Iterator for Models<'a, V, S> {
+    type Item = Model<V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.result.is_some() || self.limit.is_some_and(|l| self.found >= l) {
+            return None;
+        }
+
+        match self.encoder.solve_assuming(vec![self.guard]) {
+            SolveResult::Sat => {
+                let backend = &mut self.encoder.backend;
+                let blocking: Vec<_> = self
+                    .projection
+                    .iter()
+                    .map(|&v| if backend.value(v) { -v } else { v })
+                    .collect();
+                backend
+                    .add_clause(blocking.into_iter().chain(iter::once(-self.guard)));
+
+                self.found += 1;
+                Some(self.encoder.model())
+            }
+            result => {
+                self.result = Some(result);
+                None
+            }
+        }
+    }
+}
+
+impl<V, S: IncrementalSolver> Drop for Models<'_, V, S> {
+    fn drop(&mut self) {
+        self.encoder.backend.add_clause(iter::once(-self.guard));
+    }
+}
+
+impl<V: SatVar, S: IncrementalSolver> Encoder<V, S> {
+    /// Returns an iterator over all models which differ in the `projection`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::AtLeastK, Projection};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// encoder.add_constraint(AtLeastK { k: 1, lits: ["a", "b", "c"].iter().copied() });
+    ///
+    /// assert_eq!(encoder.models(Projection::all()).count(), 7);
+    /// assert_eq!(encoder.models(Projection::vars(vec!["a", "b"])).count(), 4);
+    /// assert_eq!(encoder.models(Projection::filter(|v| *v != "c")).limit(2).count(), 2);
+    /// # }
+    /// ```
+    pub fn models(&mut self, projection: Projection<V>) -> Models<'_, V, S> {
+        let projection = projection.resolve(&self.varmap);
+        let guard = self.varmap.new_var();
+
+        Models {
+            encoder: self,
+            projection,
+            guard,
+            limit: None,
+            found: 0,
+            result: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::CdclEncoder,
+        constraints::{AtMostK, ExactlyK},
+    };
+
+    #[test]
+    fn models_are_distinct_on_projection() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(ExactlyK { k: 2, lits: 0..6 });
+
+        let mut seen = Vec::new();
+        for model in encoder.models(Projection::vars(0..3)) {
+            let projected: Vec<_> = (0..3).map(|v| model[v]).collect();
+            assert!(!seen.contains(&projected));
+            seen.push(projected);
+        }
+        // At most two of the first three variables are true.
+        assert_eq!(seen.len(), 7);
+
+        // The blocking clauses are disabled after the iterator was dropped.
+        assert_eq!(encoder.models(Projection::all()).count(), 15);
+    }
+
+    #[test]
+    fn models_limit() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(AtMostK { k: 3, lits: 0..8 });
+
+        let mut models = encoder.models(Projection::filter(|&v| v % 2 == 0));
+        assert_eq!(models.by_ref().take(3).count(), 3);
+        assert!(!models.is_complete());
+
+        let mut models = models.limit(5);
+        assert_eq!(models.by_ref().count(), 2);
+        assert_eq!(models.found(), 5);
+        assert!(!models.is_complete());
+        drop(models);
+
+        let mut models = encoder.models(Projection::vars(vec![0, 1, 8]));
+        assert_eq!(models.by_ref().count(), 4);
+        assert!(models.is_complete());
+    }
+
+    #[test]
+    fn unknown_projection_vars_are_skipped() {
+        let mut encoder = CdclEncoder::<&str>::new();
+        encoder.add_constraint(ExactlyK {
+            k: 1,
+            lits: ["a", "b"].iter().copied(),
+        });
+
+        let projection = Projection::vars(vec!["a", "typo"]);
+        assert_eq!(encoder.models(projection).count(), 2);
+        assert_eq!(encoder.varmap.get_var("typo"), None);
+        assert_eq!(encoder.models(Projection::vars(vec!["typo"])).count(), 1);
+    }
+}
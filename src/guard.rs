@@ -0,0 +1,321 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use crate::{Backend, Constraint, Encoder, IncrementalSolver, SatVar, VarType};
+
+/// Handle of a constraint added with [`Encoder::add_guarded_constraint`].
+///
+/// The constraint only has to hold while its guard is enabled.
+/// Dropping a `Guard` leaves the constraint in its current state, use
+/// [`Encoder::retire_guard`] to remove it permanently.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Guard<V> {
+    lit: i32,
+    _var: PhantomData<V>,
+}
+
+impl<V: SatVar> Guard<V> {
+    /// Returns the activation literal of the guard.
+    /// If it is true the guarded constraint has to be satisfied.
+    pub fn repr(&self) -> VarType<V> {
+        VarType::Unnamed(self.lit)
+    }
+}
+
+/// Activation literals which are assumed in every solve call of an [`Encoder`].
+///
+/// They are passed to [`Solver::guarded_solve`](crate::Solver::guarded_solve).
+/// They are only created by methods which require an [`IncrementalSolver`], so
+/// encoders with other backends never have any.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Activation {
+    /// Activation literals of the enabled guards and open scopes.
+    pub enabled: Vec<i32>,
+    /// Activation literals of the open scopes, innermost last.
+    pub scopes: Vec<i32>,
+}
+
+/// Backend adapter which only enables each clause if all activation literals are
+/// true.
+pub(crate) struct GuardedBackend<'a, B> {
+    pub backend: &'a mut B,
+    pub guards: &'a [i32],
+}
+
+impl<B: Backend> Backend for GuardedBackend<'_, B> {
+    fn add_clause<I>(&mut self, lits: I)
+    where
+        I: Iterator<Item = i32>,
+    {
+        let guards = self.guards.iter().map(|&g| -g);
+        self.backend.add_clause(lits.chain(guards));
+    }
+
+    fn add_debug_info<D: Debug>(&mut self, debug: D) {
+        self.backend.add_debug_info(debug);
+    }
+
+    fn append_debug_info<D: Debug>(&mut self, debug: D) {
+        self.backend.append_debug_info(debug);
+    }
+}
+
+impl<V: SatVar, S: IncrementalSolver> Encoder<V, S> {
+    /// Encode a constraint which only has to hold while its guard is enabled.
+    ///
+    /// The constraint is encoded once under a fresh activation literal.
+    /// New guards are enabled, every solve call of the encoder assumes the
+    /// activation literals of all enabled guards.
+    /// Guards created inside of a scope are retired by [`pop`](Encoder::pop).
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::AtLeastK, Lit};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// encoder.add_constraint(Lit::Neg("a"));
+    /// let guard = encoder.add_guarded_constraint(AtLeastK {
+    ///     k: 2,
+    ///     lits: ["a", "b", "c"].iter().copied(),
+    /// });
+    ///
+    /// let model = encoder.solve().unwrap();
+    /// assert!(model["b"] && model["c"]);
+    ///
+    /// encoder.disable_guard(&guard);
+    /// encoder.add_constraint(Lit::Neg("b"));
+    /// assert!(encoder.solve().is_some());
+    ///
+    /// encoder.enable_guard(&guard);
+    /// assert!(encoder.solve().is_none());
+    ///
+    /// encoder.retire_guard(guard);
+    /// assert!(encoder.solve().is_some());
+    /// # }
+    /// ```
+    pub fn add_guarded_constraint<C: Constraint<V>>(
+        &mut self,
+        constraint: C,
+    ) -> Guard<V> {
+        let lit = self.varmap.new_var();
+
+        if self.debug {
+            self.backend.add_debug_info(&constraint);
+        }
+
+        let guards = match self.activation.scopes.last() {
+            Some(&scope) => vec![lit, scope],
+            None => vec![lit],
+        };
+        let mut backend = GuardedBackend {
+            backend: &mut self.backend,
+            guards: &guards,
+        };
+        constraint.encode(&mut backend, &mut self.varmap);
+
+        self.activation.enabled.push(lit);
+
+        Guard {
+            lit,
+            _var: PhantomData,
+        }
+    }
+
+    /// Enable the guarded constraint for the following solve calls.
+    pub fn enable_guard(&mut self, guard: &Guard<V>) {
+        if !self.is_guard_enabled(guard) {
+            self.activation.enabled.push(guard.lit);
+        }
+    }
+
+    /// Disable the guarded constraint for the following solve calls.
+    pub fn disable_guard(&mut self, guard: &Guard<V>) {
+        self.activation.enabled.retain(|&l| l != guard.lit);
+    }
+
+    /// Returns if the guarded constraint is enabled.
+    pub fn is_guard_enabled(&self, guard: &Guard<V>) -> bool {
+        self.activation.enabled.contains(&guard.lit)
+    }
+
+    /// Permanently disable the guarded constraint.
+    /// This allows the solver to remove its clauses.
+    pub fn retire_guard(&mut self, guard: Guard<V>) {
+        self.disable_guard(&guard);
+        self.backend.add_clause(std::iter::once(-guard.lit));
+    }
+
+    /// Open a new scope.
+    ///
+    /// All constraints added until the matching [`pop`](Encoder::pop) are removed
+    /// again once the scope is popped.
+    /// Clauses added directly to the backend are not affected.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::AtLeastK, Lit};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    /// encoder.add_constraint(AtLeastK { k: 1, lits: ["a", "b"].iter().copied() });
+    ///
+    /// encoder.push();
+    /// encoder.add_constraint(Lit::Neg("a"));
+    /// encoder.add_constraint(Lit::Neg("b"));
+    /// assert!(encoder.solve().is_none());
+    /// encoder.pop();
+    ///
+    /// assert!(encoder.solve().is_some());
+    /// # }
+    /// ```
+    pub fn push(&mut self) {
+        let lit = self.varmap.new_var();
+
+        self.activation.scopes.push(lit);
+        self.activation.enabled.push(lit);
+    }
+
+    /// Close the innermost scope and permanently remove all constraints added in
+    /// it, including soft constraints.
+    ///
+    /// Variables which were first used inside of the scope are marked as stale in the
+    /// [`VarMap`](crate::VarMap) and are left out of models until they are used
+    /// again.
+    ///
+    /// # Panics
+    /// Panics if there is no open scope.
+    pub fn pop(&mut self) {
+        let lit = self
+            .activation
+            .scopes
+            .pop()
+            .expect("pop without matching push");
+
+        // Activation literals are allocated in increasing order, so every guard of
+        // the scope has a larger literal.
+        self.activation.enabled.retain(|&l| l < lit);
+        let depth = self.activation.scopes.len();
+        self.soft.retain(|s| s.depth <= depth);
+        self.backend.add_clause(std::iter::once(-lit));
+        self.varmap.mark_stale_from(lit);
+    }
+
+    /// Number of open scopes.
+    pub fn scope_depth(&self) -> usize {
+        self.activation.scopes.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use crate::{
+        backend::{Cdcl, CdclEncoder},
+        constraints::{AtMostK, ExactlyK},
+        AssumptionSolveResult, Backend, Encoder, Lit, SolveResult, Solver,
+    };
+
+    /// Solver without support for assumptions.
+    #[derive(Default)]
+    struct PlainSolver(Cdcl);
+
+    impl Backend for PlainSolver {
+        fn add_clause<I: Iterator<Item = i32>>(&mut self, lits: I) {
+            self.0.add_clause(lits);
+        }
+
+        fn add_debug_info<D: fmt::Debug>(&mut self, _debug: D) {}
+
+        fn append_debug_info<D: fmt::Debug>(&mut self, _debug: D) {}
+    }
+
+    impl Solver for PlainSolver {
+        fn solve(&mut self) -> SolveResult {
+            self.0.solve()
+        }
+
+        fn value(&mut self, var: i32) -> bool {
+            self.0.value(var)
+        }
+    }
+
+    #[test]
+    fn solve_without_incremental_solver() {
+        let mut encoder = Encoder::<u32, PlainSolver>::new();
+        encoder.add_constraint(ExactlyK { k: 1, lits: 0..2 });
+        encoder.add_constraint(Lit::Neg(0));
+
+        assert!(encoder.solve().unwrap()[1]);
+    }
+
+    #[test]
+    fn guards_switch_constraints() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(ExactlyK { k: 2, lits: 0..4 });
+
+        let first = encoder.add_guarded_constraint(AtMostK { k: 0, lits: 0..2 });
+        let second = encoder.add_guarded_constraint(AtMostK { k: 0, lits: 2..4 });
+        assert!(encoder.solve().is_none());
+
+        encoder.disable_guard(&second);
+        let model = encoder.solve().unwrap();
+        assert!(model[2] && model[3]);
+
+        encoder.enable_guard(&second);
+        encoder.disable_guard(&first);
+        assert!(!encoder.is_guard_enabled(&first));
+        let model = encoder.solve().unwrap();
+        assert!(model[0] && model[1]);
+
+        encoder.enable_guard(&first);
+        encoder.retire_guard(second);
+        let model = encoder.solve().unwrap();
+        assert!(model[2] && model[3]);
+    }
+
+    #[test]
+    fn scopes_remove_constraints() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(ExactlyK { k: 1, lits: 0..3 });
+
+        encoder.push();
+        encoder.add_constraint(Lit::Neg(0));
+        let guard = encoder.add_guarded_constraint(Lit::Neg(1));
+
+        encoder.push();
+        encoder.add_constraint(Lit::Neg(2));
+        encoder.add_constraint(Lit::Pos(3));
+        assert_eq!(encoder.scope_depth(), 2);
+        assert!(encoder.solve().is_none());
+
+        encoder.pop();
+        assert!(encoder.varmap.is_stale(&3));
+        assert!(!encoder.varmap.is_stale(&2));
+        let model = encoder.solve().unwrap();
+        assert!(model[2]);
+        assert_eq!(model.var(3), None);
+
+        encoder.pop();
+        assert!(!encoder.is_guard_enabled(&guard));
+        encoder.add_constraint(Lit::Neg(2));
+        encoder.add_constraint(Lit::Neg(3));
+        assert!(!encoder.varmap.is_stale(&3));
+
+        let model = encoder.solve().unwrap();
+        assert!(model[0] ^ model[1]);
+        assert!(!model[3]);
+    }
+
+    #[test]
+    fn guards_are_excluded_from_cores() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        let _guard = encoder.add_guarded_constraint(Lit::Pos(0));
+
+        match encoder.solve_under(vec![Lit::Neg(0), Lit::Pos(1)]) {
+            AssumptionSolveResult::Unsat(Some(failed)) => {
+                assert_eq!(failed, vec![Lit::Neg(0)])
+            }
+            _ => panic!("expected unsat"),
+        }
+    }
+}
@@ -142,25 +142,51 @@
 //! By default Satoxid provides the [CaDiCaL](https://github.com/arminbiere/cadical) SAT solver as a backend which can be used
 //! with the [`CadicalEncoder`] type definition.
 //! This dependency can be disabled using the `cadical` feature.
+//!
+//! Without it the built-in [`Cdcl`](crate::backend::Cdcl) solver, written in pure Rust,
+//! is still available through the [`CdclEncoder`](crate::backend::CdclEncoder) type
+//! definition.
 
 use core::fmt;
 use std::{
     fmt::Debug,
     hash::Hash,
     ops::{Index, Not},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
 pub mod constraints;
 
+mod approx;
+mod backbone;
 mod circuit;
+mod counting;
+mod enumerate;
+mod guard;
+mod mus;
+mod optimize;
+mod pareto;
+mod propagate;
 mod varmap;
 
 use backend::mock::MockSolver;
+pub use enumerate::{Models, Projection};
+pub use guard::Guard;
+use guard::{Activation, GuardedBackend};
+pub use mus::{Marco, Subset};
+use optimize::Soft;
+pub use optimize::{Bounds, Objective};
+pub use pareto::ParetoFront;
+pub use propagate::Conflict;
 pub use varmap::VarMap;
 
-mod backend;
+pub mod backend;
 
-use ahash::{HashSet, HashSetExt};
+use ahash::HashSet;
 pub use backend::DimacsWriter;
 
 #[cfg(feature = "cadical")]
@@ -202,6 +228,33 @@ pub trait Solver: Backend {
     /// This function should panic if solve wasn't called previously or wasn't able to
     /// solve the problem.
     fn value(&mut self, var: i32) -> bool;
+
+    /// Statistics of the last solve call.
+    /// Backends which don't keep track of statistics return zeros.
+    fn stats(&self) -> SolveStats {
+        SolveStats::default()
+    }
+
+    /// Solve the problem assuming the activation literals `guards`, which are never
+    /// empty.
+    ///
+    /// Guards are only created for an [`IncrementalSolver`], which passes them on to
+    /// [`assumption_solve`](IncrementalSolver::assumption_solve). Other solvers keep
+    /// the default implementation, which panics.
+    fn guarded_solve(&mut self, guards: &[i32]) -> SolveResult {
+        let _ = guards;
+        panic!("guards require an IncrementalSolver backend")
+    }
+}
+
+/// Search statistics of a single solve call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SolveStats {
+    pub time: Duration,
+    pub conflicts: u64,
+    pub decisions: u64,
+    pub propagations: u64,
+    pub restarts: u64,
 }
 
 pub trait IncrementalSolver: Solver {
@@ -210,6 +263,105 @@ pub trait IncrementalSolver: Solver {
         I: Iterator<Item = i32>;
 }
 
+/// Resource limits of a single solve call.
+///
+/// If the time limit is reached or the `terminate` flag is set, the solver stops with
+/// [`SolveResult::Interrupted`].
+/// If the conflict or decision budget is exhausted, it stops with
+/// [`SolveResult::Unknown`].
+///
+/// # Example
+/// ```rust
+/// # use std::{sync::{atomic::{AtomicBool, Ordering}, Arc}, time::Duration};
+/// # use satoxid::SolveLimits;
+/// let terminate = Arc::new(AtomicBool::new(false));
+///
+/// let limits = SolveLimits::new()
+///     .time(Duration::from_secs(10))
+///     .conflicts(100_000)
+///     .terminate(Arc::clone(&terminate));
+///
+/// // Some other thread can stop the search early.
+/// terminate.store(true, Ordering::Relaxed);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SolveLimits {
+    pub time: Option<Duration>,
+    pub conflicts: Option<u64>,
+    pub decisions: Option<u64>,
+    pub terminate: Option<Arc<AtomicBool>>,
+}
+
+impl SolveLimits {
+    /// Creates limits which don't restrict the solver.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the wall-clock time of the solve call.
+    pub fn time(mut self, time: Duration) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Limit the number of conflicts of the solve call.
+    pub fn conflicts(mut self, conflicts: u64) -> Self {
+        self.conflicts = Some(conflicts);
+        self
+    }
+
+    /// Limit the number of decisions of the solve call.
+    pub fn decisions(mut self, decisions: u64) -> Self {
+        self.decisions = Some(decisions);
+        self
+    }
+
+    /// Stop the solve call as soon as `flag` is set to true.
+    pub fn terminate(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.terminate = Some(flag);
+        self
+    }
+}
+
+/// A trait for backends which keep the clauses added to them.
+///
+/// It is implemented by [`Cdcl`](backend::Cdcl), [`DimacsWriter`] and
+/// [`ExternalSolver`](backend::ExternalSolver). Other solvers can be wrapped in a
+/// [`Recorder`](backend::Recorder), like CaDiCaL in the
+/// [`RecordingCadicalEncoder`](backend::RecordingCadicalEncoder).
+pub trait ClauseDatabase: Backend {
+    /// Returns a set of clauses equivalent to all clauses added so far.
+    /// Backends may simplify the clauses, but the set of models has to stay the same.
+    fn clauses(&self) -> Vec<Vec<i32>>;
+
+    /// Assigns `assumptions` in the given order and applies unit propagation to the
+    /// clauses. The assignment is undone afterwards.
+    ///
+    /// Returns all assigned literals, including the assumptions, in the order in
+    /// which they were assigned. On a conflict the assumptions involved are returned,
+    /// they are empty if the clauses conflict without any assumptions.
+    ///
+    /// The default implementation builds the watch lists from
+    /// [`clauses`](ClauseDatabase::clauses) on every call.
+    fn propagate_assumptions(
+        &mut self,
+        assumptions: &[i32],
+    ) -> Result<Vec<i32>, Vec<i32>> {
+        let mut propagator = propagate::Propagator::default();
+        for clause in self.clauses() {
+            propagator.add_clause(clause);
+        }
+        propagator.propagate(assumptions)
+    }
+}
+
+/// A trait for solvers which are able to stop early.
+pub trait LimitedSolver: Solver {
+    /// Set the limits which apply to each of the following solve calls.
+    /// Limits a backend isn't able to enforce are ignored.
+    fn set_limits(&mut self, limits: SolveLimits);
+}
+
 /// Trait used to express a constraint.
 /// Constraints generate a finite set of clauses which are passed to the given backend.
 pub trait Constraint<V: SatVar>: Debug + Sized + Clone {
@@ -540,6 +692,8 @@ pub struct Encoder<V, S> {
     pub backend: S,
     pub varmap: VarMap<V>,
     pub debug: bool,
+    activation: Activation,
+    soft: Vec<Soft>,
 }
 
 impl<V: SatVar, S: Default> Encoder<V, S> {
@@ -549,6 +703,8 @@ impl<V: SatVar, S: Default> Encoder<V, S> {
             backend: S::default(),
             varmap: VarMap::default(),
             debug: false,
+            activation: Activation::default(),
+            soft: Vec::new(),
         }
     }
 
@@ -558,6 +714,8 @@ impl<V: SatVar, S: Default> Encoder<V, S> {
             backend: S::default(),
             varmap: VarMap::default(),
             debug: true,
+            activation: Activation::default(),
+            soft: Vec::new(),
         }
     }
 }
@@ -587,15 +745,35 @@ where
             backend,
             varmap: VarMap::default(),
             debug: false,
+            activation: Activation::default(),
+            soft: Vec::new(),
         }
     }
 
     /// Encode a constraint.
+    ///
+    /// Inside of a scope the constraint is removed again by [`pop`](Encoder::pop).
     pub fn add_constraint<C: Constraint<V>>(&mut self, constraint: C) {
         if self.debug {
             self.backend.add_debug_info(&constraint);
         }
-        constraint.encode(&mut self.backend, &mut self.varmap);
+
+        let (mut backend, varmap) = self.scoped();
+        constraint.encode(&mut backend, varmap);
+    }
+
+    /// Returns the backend guarded by the innermost scope.
+    fn scoped(&mut self) -> (GuardedBackend<'_, B>, &mut VarMap<V>) {
+        let guards = match self.activation.scopes.last() {
+            Some(scope) => std::slice::from_ref(scope),
+            None => &[],
+        };
+
+        let backend = GuardedBackend {
+            backend: &mut self.backend,
+            guards,
+        };
+        (backend, &mut self.varmap)
     }
 
     /// Encode a constraint such that a variable represents it.
@@ -610,11 +788,9 @@ where
             self.backend.add_debug_info(&constraint);
         }
 
-        let repr = constraint.encode_constraint_implies_repr(
-            None,
-            &mut self.backend,
-            &mut self.varmap,
-        );
+        let (mut backend, varmap) = self.scoped();
+        let repr =
+            constraint.encode_constraint_implies_repr(None, &mut backend, varmap);
 
         if self.debug {
             self.backend
@@ -636,11 +812,9 @@ where
             self.backend.add_debug_info(&constraint);
         }
 
-        let repr = constraint.encode_constraint_equals_repr(
-            None,
-            &mut self.backend,
-            &mut self.varmap,
-        );
+        let (mut backend, varmap) = self.scoped();
+        let repr =
+            constraint.encode_constraint_equals_repr(None, &mut backend, varmap);
 
         if self.debug {
             self.backend
@@ -651,37 +825,219 @@ where
     }
 }
 
+/// Result of [`Encoder::solve_outcome`].
+#[derive(Clone)]
+pub enum SolveOutcome<V> {
+    /// The problem is satisfiable.
+    Sat(Model<V>),
+    /// The problem is proven to be unsatisfiable.
+    Unsat,
+    /// The solver gave up, e.g. because a conflict or decision budget ran out.
+    Unknown,
+    /// The solver was stopped by a time limit or termination request.
+    Interrupted,
+}
+
+impl<V> SolveOutcome<V> {
+    /// Returns the model if the problem was satisfiable.
+    pub fn into_model(self) -> Option<Model<V>> {
+        match self {
+            SolveOutcome::Sat(model) => Some(model),
+            _ => None,
+        }
+    }
+}
+
 impl<V: SatVar, S: Solver> Encoder<V, S> {
     /// Solve the encoded problem.
     /// If problem is unsat then `None` is returned.
     /// Otherwise a model of the problem is returned.
+    ///
+    /// Use [`solve_outcome`](Encoder::solve_outcome) to tell apart unsatisfiable
+    /// problems from solver failures.
     pub fn solve(&mut self) -> Option<Model<V>> {
-        let result = self.backend.solve();
-        if let SolveResult::Sat = result {
-            let assignments = self
-                .varmap
-                .iter_internal_vars()
-                .map(|v| {
-                    let v = v as i32;
-                    let assignment = self.backend.value(v);
-
-                    if let Some(var) = self.varmap.lookup(v) {
-                        let var = var.unwrap();
-                        let lit = if assignment {
-                            Lit::Pos(var)
-                        } else {
-                            Lit::Neg(var)
-                        };
-                        VarType::Named(lit)
-                    } else {
-                        let lit = if assignment { v } else { -v };
-                        VarType::Unnamed(lit)
-                    }
-                })
-                .collect();
-            Some(Model { assignments })
+        self.solve_outcome().into_model()
+    }
+
+    /// Solve the encoded problem.
+    /// Unlike [`solve`](Encoder::solve) this reports why no model was found.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::AtLeastK, Lit, SolveOutcome};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// encoder.add_constraint(AtLeastK { k: 2, lits: ["a", "b"].iter().copied() });
+    /// encoder.add_constraint(Lit::Neg("a"));
+    ///
+    /// assert!(matches!(encoder.solve_outcome(), SolveOutcome::Unsat));
+    /// println!("{:?}", encoder.stats());
+    /// # }
+    /// ```
+    pub fn solve_outcome(&mut self) -> SolveOutcome<V> {
+        let result = self.solve_active();
+        self.outcome(result)
+    }
+
+    /// Solves the problem assuming the activation literals of all enabled guards.
+    fn solve_active(&mut self) -> SolveResult {
+        if self.activation.enabled.is_empty() {
+            self.backend.solve()
         } else {
-            None
+            self.backend.guarded_solve(&self.activation.enabled)
+        }
+    }
+
+    /// Statistics of the last solve call of the backend.
+    pub fn stats(&self) -> SolveStats {
+        self.backend.stats()
+    }
+
+    fn outcome(&mut self, result: SolveResult) -> SolveOutcome<V> {
+        match result {
+            SolveResult::Sat => SolveOutcome::Sat(self.model()),
+            SolveResult::Unsat(_) => SolveOutcome::Unsat,
+            SolveResult::Unknown => SolveOutcome::Unknown,
+            SolveResult::Interrupted => SolveOutcome::Interrupted,
+        }
+    }
+
+    /// Builds the model of the last successful solve call.
+    fn model(&mut self) -> Model<V> {
+        let assignments = self
+            .varmap
+            .iter_internal_vars()
+            .filter_map(|v| {
+                if self.varmap.is_stale_id(v) {
+                    return None;
+                }
+
+                let assignment = self.backend.value(v);
+
+                if let Some(var) = self.varmap.lookup(v) {
+                    let var = var.unwrap();
+                    let lit = if assignment {
+                        Lit::Pos(var)
+                    } else {
+                        Lit::Neg(var)
+                    };
+                    Some(VarType::Named(lit))
+                } else {
+                    let lit = if assignment { v } else { -v };
+                    Some(VarType::Unnamed(lit))
+                }
+            })
+            .collect();
+        Model { assignments }
+    }
+}
+
+impl<V: SatVar, S: LimitedSolver> Encoder<V, S> {
+    /// Solve the encoded problem within the given resource limits.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::ExactlyK, SolveLimits, SolveOutcome};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// // Pigeonhole problem: 8 pigeons don't fit in 7 holes.
+    /// for pigeon in 0..8 {
+    ///     encoder.add_constraint(ExactlyK { k: 1, lits: (0..7).map(|h| (pigeon, h)) });
+    /// }
+    /// for hole in 0..7 {
+    ///     encoder.add_constraint(ExactlyK { k: 1, lits: (0..8).map(|p| (p, hole)) });
+    /// }
+    ///
+    /// let outcome = encoder.solve_with_limits(SolveLimits::new().conflicts(10));
+    /// assert!(matches!(outcome, SolveOutcome::Unknown));
+    /// # }
+    /// ```
+    pub fn solve_with_limits(&mut self, limits: SolveLimits) -> SolveOutcome<V> {
+        let start = Instant::now();
+        let time = limits.time;
+        let terminate = limits.terminate.clone();
+
+        self.backend.set_limits(limits);
+        let result = self.solve_active();
+        self.backend.set_limits(SolveLimits::default());
+
+        // Not every backend is able to tell apart the reasons why it stopped.
+        let interrupted = time.is_some_and(|t| start.elapsed() >= t)
+            || terminate.is_some_and(|flag| flag.load(Ordering::Relaxed));
+
+        match result {
+            SolveResult::Unknown if interrupted => SolveOutcome::Interrupted,
+            result => self.outcome(result),
+        }
+    }
+}
+
+impl<V: SatVar, S: IncrementalSolver> Encoder<V, S> {
+    /// Solve the encoded problem assuming all `lits` are true.
+    ///
+    /// The literals are passed straight to the solver, no clauses are added.
+    /// If the problem is unsatisfiable under the assumptions, the failed assumptions
+    /// are returned.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::AtMostK, Lit, AssumptionSolveResult};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// encoder.add_constraint(AtMostK { k: 1, lits: ["a", "b", "c"].iter().copied() });
+    ///
+    /// let result = encoder.solve_under(vec![Lit::Pos("a"), Lit::Neg("c"), Lit::Pos("b")]);
+    /// match result {
+    ///     AssumptionSolveResult::Unsat(Some(mut failed)) => {
+    ///         failed.sort();
+    ///         assert_eq!(failed, vec![Lit::Pos("a"), Lit::Pos("b")]);
+    ///     }
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// let result = encoder.solve_under(Some(Lit::Pos("c")));
+    /// assert!(matches!(result, AssumptionSolveResult::Sat(_)));
+    /// # }
+    /// ```
+    pub fn solve_under<I>(&mut self, lits: I) -> AssumptionSolveResult<V, Lit<V>>
+    where
+        I: IntoIterator<Item = Lit<V>>,
+    {
+        let assumptions: Vec<_> = lits
+            .into_iter()
+            .map(|lit| self.varmap.add_var(lit))
+            .collect();
+
+        match self.solve_assuming(assumptions) {
+            SolveResult::Sat => AssumptionSolveResult::Sat(self.model()),
+            SolveResult::Unsat(Some(core)) => {
+                let failed = core
+                    .into_iter()
+                    .map(|lit| self.varmap.lookup(lit).unwrap())
+                    .collect();
+                AssumptionSolveResult::Unsat(Some(failed))
+            }
+            SolveResult::Unsat(None) => AssumptionSolveResult::Unsat(None),
+            SolveResult::Interrupted => AssumptionSolveResult::Interrupted,
+            SolveResult::Unknown => AssumptionSolveResult::Unknown,
+        }
+    }
+
+    /// Solves the problem under `assumptions` and the activation literals of all
+    /// enabled guards. Activation literals are removed from the returned core.
+    fn solve_assuming(&mut self, assumptions: Vec<i32>) -> SolveResult {
+        let active = &self.activation.enabled;
+        let all = active.iter().copied().chain(assumptions);
+
+        match self.backend.assumption_solve(all) {
+            SolveResult::Unsat(Some(mut core)) => {
+                core.retain(|lit| !active.contains(lit));
+                SolveResult::Unsat(Some(core))
+            }
+            result => result,
         }
     }
 }
@@ -732,18 +1088,19 @@ impl<V: SatVar, S: IncrementalSolver> AssumptionSolver<V> for Encoder<V, S> {
             let aux = self.varmap.new_var();
             aux_literals.push(aux);
 
-            // 3) (-aux ∨ clause) を追加
+            // 3) (-aux ∨ clause) を追加 (スコープ内ならスコープのガードも付く)
+            let (mut backend, _) = self.scoped();
             for clause in &clauses {
                 let mut guarded = vec![-aux];
                 guarded.extend(clause.iter().copied());
-                self.backend.add_clause(guarded.into_iter());
+                backend.add_clause(guarded.into_iter());
             }
 
             aux2constraint.insert(aux, (constraint, clauses));
         }
 
         // --- 2. solve ----------------------------------------------------------
-        match self.backend.assumption_solve(aux_literals.iter().copied()) {
+        match self.solve_assuming(aux_literals) {
             // ---------- SAT ----------
             SolveResult::Sat => {
                 // モデルを組み立て
@@ -751,9 +1108,12 @@ impl<V: SatVar, S: IncrementalSolver> AssumptionSolver<V> for Encoder<V, S> {
                     .varmap
                     .iter_internal_vars()
                     .filter_map(|v| {
+                        if self.varmap.is_stale_id(v) {
+                            return None;
+                        }
                         // lookup が Some のときだけ評価
-                        self.varmap.lookup(v as i32).map(|var| {
-                            let val = self.backend.value(v as i32);
+                        self.varmap.lookup(v).map(|var| {
+                            let val = self.backend.value(v);
                             let lit = if val {
                                 Lit::Pos(var.unwrap())
                             } else {
@@ -766,9 +1126,10 @@ impl<V: SatVar, S: IncrementalSolver> AssumptionSolver<V> for Encoder<V, S> {
 
                 // 要求があればガード無し節を永続化
                 if commit_if_sat {
+                    let (mut backend, _) = self.scoped();
                     for (_, clauses) in aux2constraint.values() {
                         for clause in clauses {
-                            self.backend.add_clause(clause.iter().copied());
+                            backend.add_clause(clause.iter().copied());
                         }
                     }
                 }
@@ -784,6 +1145,7 @@ impl<V: SatVar, S: IncrementalSolver> AssumptionSolver<V> for Encoder<V, S> {
 
                 // 2. 失敗した制約を集めつつ，成功した制約を commit
                 let mut failed = Vec::<C>::new();
+                let (mut backend, _) = self.scoped();
                 for (aux, (constraint, clauses)) in &aux2constraint {
                     if core_aux.contains(aux) {
                         // → UNSAT の原因：failed へ
@@ -791,7 +1153,7 @@ impl<V: SatVar, S: IncrementalSolver> AssumptionSolver<V> for Encoder<V, S> {
                     } else if commit_if_sat {
                         // → 満たされた制約：guard を外した節を恒久追加
                         for clause in clauses {
-                            self.backend.add_clause(clause.iter().copied());
+                            backend.add_clause(clause.iter().copied());
                         }
                     }
                 }
@@ -0,0 +1,444 @@
+use std::iter;
+
+use crate::{
+    backend::Cdcl, guard::GuardedBackend, AssumptionSolveResult, Backend,
+    Constraint, Encoder, IncrementalSolver, SatVar, SolveResult, Solver,
+};
+
+/// Subset of labelled constraints found by [`Marco`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Subset<C> {
+    /// Minimal unsatisfiable subset.
+    Mus(Vec<C>),
+    /// Minimal correction set: removing these constraints makes the rest
+    /// satisfiable, removing any smaller subset of them doesn't.
+    Mcs(Vec<C>),
+}
+
+/// Iterator over all minimal unsatisfiable subsets and minimal correction sets of a
+/// list of constraints, created by [`Encoder::marco`].
+///
+/// A separate map solver keeps track of the subsets of constraints which weren't
+/// explored yet. Each of its models is a seed which is either grown to a maximal
+/// satisfiable subset, whose complement is a correction set, or shrunk to a minimal
+/// unsatisfiable subset. Both results block the seeds they cover.
+/// The constraints are disabled once the iterator is dropped.
+pub struct Marco<'a, V, S: IncrementalSolver, C> {
+    encoder: &'a mut Encoder<V, S>,
+    labelled: Vec<(i32, C)>,
+    map: Cdcl,
+    result: Option<SolveResult>,
+}
+
+impl<V: SatVar, S: IncrementalSolver, C: Constraint<V>> Marco<'_, V, S, C> {
+    /// Returns true if all subsets were enumerated.
+    /// This is false if the solver failed.
+    pub fn is_complete(&self) -> bool {
+        matches!(self.result, Some(SolveResult::Unsat(_)))
+    }
+
+    /// Returns the constraints at the given indices.
+    fn subset(&self, indices: &[usize]) -> Vec<C> {
+        indices
+            .iter()
+            .map(|&i| self.labelled[i].1.clone())
+            .collect()
+    }
+
+    /// Grows the satisfiable `seed` to a maximal satisfiable subset.
+    fn grow(&mut self, mut seed: Vec<bool>) -> Result<Vec<bool>, SolveResult> {
+        for i in 0..seed.len() {
+            if seed[i] {
+                continue;
+            }
+
+            seed[i] = true;
+            match self.encoder.solve_assuming(self.labels(&seed)) {
+                SolveResult::Sat => {}
+                SolveResult::Unsat(_) => seed[i] = false,
+                result => return Err(result),
+            }
+        }
+        Ok(seed)
+    }
+
+    fn labels(&self, subset: &[bool]) -> Vec<i32> {
+        self.labelled
+            .iter()
+            .zip(subset)
+            .filter(|(_, &selected)| selected)
+            .map(|((label, _), _)| *label)
+            .collect()
+    }
+
+    fn explore(&mut self) -> Result<Subset<C>, SolveResult> {
+        match self.map.solve() {
+            SolveResult::Sat => {}
+            result => return Err(result),
+        }
+
+        let seed: Vec<_> = (1..=self.labelled.len() as i32)
+            .map(|v| self.map.value(v))
+            .collect();
+        let labels = self.labels(&seed);
+
+        match self.encoder.solve_assuming(labels.clone()) {
+            SolveResult::Sat => {
+                let mss = self.grow(seed)?;
+                let mcs: Vec<_> = (0..mss.len()).filter(|&i| !mss[i]).collect();
+
+                // Some constraint of the correction set has to be part of every
+                // following seed.
+                self.map.add_clause(mcs.iter().map(|&i| i as i32 + 1));
+                Ok(Subset::Mcs(self.subset(&mcs)))
+            }
+            SolveResult::Unsat(_) => {
+                let mus = match self.encoder.shrink_core(&labels) {
+                    SolveResult::Unsat(Some(mus)) => mus,
+                    SolveResult::Sat => unreachable!("the seed is unsatisfiable"),
+                    result => return Err(result),
+                };
+                let mus: Vec<_> = (0..self.labelled.len())
+                    .filter(|&i| mus.contains(&self.labelled[i].0))
+                    .collect();
+
+                // No following seed contains the whole unsatisfiable subset.
+                self.map.add_clause(mus.iter().map(|&i| -(i as i32 + 1)));
+                Ok(Subset::Mus(self.subset(&mus)))
+            }
+            result => Err(result),
+        }
+    }
+}
+
+impl<V: SatVar, S: IncrementalSolver, C: Constraint<V>> Iterator
+    for Marco<'_, V, S, C>
+{
+    type Item = Subset<C>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.result.is_some() {
+            return None;
+        }
+
+        match self.explore() {
+            Ok(subset) => Some(subset),
+            Err(result) => {
+                self.result = Some(result);
+                None
+            }
+        }
+    }
+}
+
+impl<V, S: IncrementalSolver, C> Drop for Marco<'_, V, S, C> {
+    fn drop(&mut self) {
+        for (label, _) in &self.labelled {
+            self.encoder.backend.add_clause(iter::once(-label));
+        }
+    }
+}
+
+impl<V: SatVar, S: IncrementalSolver> Encoder<V, S> {
+    /// Returns a minimal subset of `constraints` which is unsatisfiable together
+    /// with the constraints already added to the encoder.
+    ///
+    /// Each constraint is encoded under its own activation literal.
+    /// Starting with the core of the first solve call, constraints are removed one
+    /// at a time. If the rest is still unsatisfiable the constraint is dropped and
+    /// the candidates are narrowed down to the new core, otherwise it is part of
+    /// the result.
+    /// The constraints are disabled afterwards.
+    ///
+    /// Returns [`AssumptionSolveResult::Sat`] if all constraints can be satisfied
+    /// at the same time.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::AtMostK, AssumptionSolveResult, Lit};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// encoder.add_constraint(AtMostK { k: 1, lits: ["a", "b", "c"].iter().copied() });
+    ///
+    /// let constraints = vec![Lit::Pos("a"), Lit::Pos("d"), Lit::Pos("b"), Lit::Pos("c")];
+    /// match encoder.minimal_unsat_core(constraints) {
+    ///     AssumptionSolveResult::Unsat(Some(core)) => {
+    ///         assert_eq!(core, vec![Lit::Pos("a"), Lit::Pos("b")]);
+    ///     }
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// assert!(encoder.solve().is_some());
+    /// # }
+    /// ```
+    pub fn minimal_unsat_core<I, C>(
+        &mut self,
+        constraints: I,
+    ) -> AssumptionSolveResult<V, C>
+    where
+        I: IntoIterator<Item = C>,
+        C: Constraint<V>,
+    {
+        let labelled = self.add_labelled(constraints);
+        let labels: Vec<_> = labelled.iter().map(|(l, _)| *l).collect();
+
+        let result = match self.shrink_core(&labels) {
+            SolveResult::Sat => AssumptionSolveResult::Sat(self.model()),
+            SolveResult::Unsat(Some(core)) => {
+                let core = labelled
+                    .into_iter()
+                    .filter(|(l, _)| core.contains(l))
+                    .map(|(_, c)| c)
+                    .collect();
+                AssumptionSolveResult::Unsat(Some(core))
+            }
+            SolveResult::Unsat(None) => AssumptionSolveResult::Unsat(None),
+            SolveResult::Interrupted => AssumptionSolveResult::Interrupted,
+            SolveResult::Unknown => AssumptionSolveResult::Unknown,
+        };
+
+        for label in labels {
+            self.backend.add_clause(iter::once(-label));
+        }
+        result
+    }
+
+    /// Enumerates the minimal unsatisfiable subsets and minimal correction sets of
+    /// `constraints`, together with the constraints already added to the encoder.
+    ///
+    /// See [`Marco`] for details.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::AtMostK, Lit, Subset};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// encoder.add_constraint(AtMostK { k: 1, lits: ["a", "b", "c"].iter().copied() });
+    ///
+    /// let constraints = vec![Lit::Pos("a"), Lit::Pos("b"), Lit::Pos("c")];
+    /// let subsets: Vec<_> = encoder.marco(constraints).collect();
+    ///
+    /// // Each pair is unsatisfiable and each pair has to be removed.
+    /// let muses = subsets.iter().filter(|s| matches!(s, Subset::Mus(_))).count();
+    /// assert_eq!(muses, 3);
+    /// assert!(subsets.contains(&Subset::Mcs(vec![Lit::Pos("a"), Lit::Pos("c")])));
+    /// assert_eq!(subsets.len(), 6);
+    /// # }
+    /// ```
+    pub fn marco<I, C>(&mut self, constraints: I) -> Marco<'_, V, S, C>
+    where
+        I: IntoIterator<Item = C>,
+        C: Constraint<V>,
+    {
+        let labelled = self.add_labelled(constraints);
+
+        Marco {
+            encoder: self,
+            labelled,
+            map: Cdcl::new(),
+            result: None,
+        }
+    }
+
+    /// Enumerates the minimal correction sets of `constraints`.
+    ///
+    /// Removing the constraints of a correction set makes the problem satisfiable.
+    /// This uses [`marco`](Encoder::marco) and skips the unsatisfiable subsets.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::AtMostK, Lit};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// encoder.add_constraint(AtMostK { k: 2, lits: ["a", "b", "c"].iter().copied() });
+    ///
+    /// let constraints = vec![Lit::Pos("a"), Lit::Pos("b"), Lit::Pos("c"), Lit::Neg("d")];
+    /// let mut relaxations: Vec<_> = encoder.correction_sets(constraints).collect();
+    /// relaxations.sort_by_key(|mcs| mcs[0]);
+    ///
+    /// assert_eq!(
+    ///     relaxations,
+    ///     vec![vec![Lit::Pos("a")], vec![Lit::Pos("b")], vec![Lit::Pos("c")]]
+    /// );
+    /// # }
+    /// ```
+    pub fn correction_sets<I, C>(
+        &mut self,
+        constraints: I,
+    ) -> impl Iterator<Item = Vec<C>> + '_
+    where
+        I: IntoIterator<Item = C>,
+        C: Constraint<V> + 'static,
+    {
+        self.marco(constraints).filter_map(|subset| match subset {
+            Subset::Mcs(mcs) => Some(mcs),
+            Subset::Mus(_) => None,
+        })
+    }
+
+    /// Encodes each constraint under a fresh activation literal.
+    pub(crate) fn add_labelled<I, C>(&mut self, constraints: I) -> Vec<(i32, C)>
+    where
+        I: IntoIterator<Item = C>,
+        C: Constraint<V>,
+    {
+        constraints
+            .into_iter()
+            .map(|constraint| {
+                let label = self.varmap.new_var();
+
+                let guards = match self.activation.scopes.last() {
+                    Some(&scope) => vec![label, scope],
+                    None => vec![label],
+                };
+                let mut backend = GuardedBackend {
+                    backend: &mut self.backend,
+                    guards: &guards,
+                };
+                constraint.clone().encode(&mut backend, &mut self.varmap);
+
+                (label, constraint)
+            })
+            .collect()
+    }
+
+    /// Deletion-based minimization of the activation literals `labels`.
+    ///
+    /// Returns `Unsat(Some(mus))` with a minimal unsatisfiable subset of `labels`,
+    /// `Sat` if all of them are satisfiable or the result of a failed solve call.
+    pub(crate) fn shrink_core(&mut self, labels: &[i32]) -> SolveResult {
+        let mut core = match self.solve_assuming(labels.to_vec()) {
+            SolveResult::Unsat(Some(core)) => core,
+            SolveResult::Unsat(None) => labels.to_vec(),
+            result => return result,
+        };
+        // Keep the order of `labels`, the backend may return the core in any order.
+        core = labels
+            .iter()
+            .copied()
+            .filter(|l| core.contains(l))
+            .collect();
+
+        let mut i = 0;
+        while i < core.len() {
+            let mut rest = core.clone();
+            let removed = rest.remove(i);
+
+            match self.solve_assuming(rest.clone()) {
+                SolveResult::Sat => i += 1,
+                SolveResult::Unsat(refined) => {
+                    // Clause-set refinement: everything outside of the new core is
+                    // dropped as well.
+                    let refined = refined.unwrap_or_else(|| rest.clone());
+                    core =
+                        rest.into_iter().filter(|l| refined.contains(l)).collect();
+                    debug_assert!(!core.contains(&removed));
+                }
+                result => return result,
+            }
+        }
+
+        SolveResult::Unsat(Some(core))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backend::CdclEncoder,
+        constraints::{AtLeastK, AtMostK},
+        AssumptionSolveResult, Lit, Subset,
+    };
+
+    #[test]
+    fn minimal_core_of_cardinalities() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(AtMostK { k: 3, lits: 0..10 });
+
+        let constraints = vec![
+            AtLeastK { k: 2, lits: 0..4 },
+            AtLeastK { k: 1, lits: 10..12 },
+            AtLeastK { k: 2, lits: 4..8 },
+            AtLeastK { k: 3, lits: 0..6 },
+            AtLeastK { k: 1, lits: 8..10 },
+        ];
+
+        match encoder.minimal_unsat_core(constraints) {
+            AssumptionSolveResult::Unsat(Some(core)) => {
+                let core: Vec<_> = core.iter().map(|c| c.lits.clone()).collect();
+                // Both subsets are minimal.
+                assert!(
+                    core == vec![0..4, 4..8, 8..10] || core == vec![0..6, 8..10]
+                );
+            }
+            _ => panic!("constraints should be unsatisfiable"),
+        }
+
+        // The constraints are disabled again.
+        assert!(encoder.solve().is_some());
+
+        let satisfiable = vec![Lit::Pos(0), Lit::Neg(1)];
+        assert!(matches!(
+            encoder.minimal_unsat_core(satisfiable),
+            AssumptionSolveResult::Sat(_)
+        ));
+    }
+
+    #[test]
+    fn marco_enumerates_all_subsets() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(AtMostK { k: 2, lits: 0..4 });
+
+        let constraints = vec![
+            AtLeastK { k: 2, lits: 0..2 },
+            AtLeastK { k: 2, lits: 1..3 },
+            AtLeastK { k: 1, lits: 2..4 },
+            AtLeastK { k: 1, lits: 4..6 },
+        ];
+
+        let mut marco = encoder.marco(constraints);
+        let mut muses = Vec::new();
+        let mut mcses = Vec::new();
+        for subset in marco.by_ref() {
+            match subset {
+                Subset::Mus(mus) => muses.push(starts(&mus)),
+                Subset::Mcs(mcs) => mcses.push(starts(&mcs)),
+            }
+        }
+        assert!(marco.is_complete());
+        drop(marco);
+
+        muses.sort();
+        mcses.sort();
+        assert_eq!(muses, vec![vec![0, 1], vec![0, 2]]);
+        assert_eq!(mcses, vec![vec![0], vec![1, 2]]);
+
+        assert!(encoder.solve().is_some());
+    }
+
+    fn starts(constraints: &[AtLeastK<std::ops::Range<u32>>]) -> Vec<u32> {
+        constraints.iter().map(|c| c.lits.start).collect()
+    }
+
+    #[test]
+    fn minimal_core_without_constraints() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(AtMostK { k: 0, lits: 0..2 });
+        let guard = encoder.add_guarded_constraint(Lit::Pos(1));
+
+        let result = encoder.minimal_unsat_core(vec![Lit::Pos(2), Lit::Neg(3)]);
+        assert!(
+            matches!(result, AssumptionSolveResult::Unsat(Some(core)) if core.is_empty())
+        );
+
+        encoder.disable_guard(&guard);
+        let result = encoder.minimal_unsat_core(vec![Lit::Pos(2), Lit::Pos(0)]);
+        assert!(matches!(
+            result,
+            AssumptionSolveResult::Unsat(Some(core)) if core == vec![Lit::Pos(0)]
+        ));
+    }
+}
@@ -0,0 +1,686 @@
+use std::iter;
+
+use crate::{
+    circuit::Direction,
+    constraints::{
+        totalizer::{encode_generalized_totalizer, Totalizer},
+        Not,
+    },
+    guard::GuardedBackend,
+    Backend, ConstraintRepr, Encoder, IncrementalSolver, Model, SatVar, SolveResult,
+    VarMap, VarType,
+};
+
+/// Soft constraint added with [`Encoder::add_soft`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Soft {
+    /// Literal which is only true if the constraint is satisfied.
+    pub lit: i32,
+    pub weight: u64,
+    /// Number of open scopes when the constraint was added.
+    pub depth: usize,
+}
+
+impl<V: SatVar, S: Backend> Encoder<V, S> {
+    /// Add a constraint which should be satisfied, but may be violated for a cost
+    /// of `weight`.
+    ///
+    /// The negated constraint is reified with
+    /// [`encode_constraint_implies_repr`](ConstraintRepr::encode_constraint_implies_repr),
+    /// the returned literal is the negation of its repr. So it is only true if the
+    /// constraint is satisfied.
+    /// Soft constraints added inside of a scope are removed by
+    /// [`pop`](Encoder::pop).
+    ///
+    /// See [`solve_optimal`](Encoder::solve_optimal).
+    pub fn add_soft<C: ConstraintRepr<V>>(
+        &mut self,
+        constraint: C,
+        weight: u64,
+    ) -> VarType<V> {
+        if self.debug {
+            self.backend.add_debug_info(&constraint);
+        }
+
+        // The repr of the negation is true if the constraint is violated.
+        let (mut backend, varmap) = self.scoped();
+        let violated = Not(constraint).encode_constraint_implies_repr(
+            None,
+            &mut backend,
+            varmap,
+        );
+        let lit = -violated;
+
+        self.soft.push(Soft {
+            lit,
+            weight,
+            depth: self.activation.scopes.len(),
+        });
+        VarType::Unnamed(lit)
+    }
+}
+
+impl<V: SatVar, S: IncrementalSolver> Encoder<V, S> {
+    /// Solve the problem such that the weight of the violated soft constraints is
+    /// minimal.
+    ///
+    /// Returns an optimal model and its cost.
+    /// Returns `None` if the hard constraints are unsatisfiable or the solver
+    /// failed before optimality was proven.
+    ///
+    /// This is a linear SAT-UNSAT search: after each model a pseudo-boolean bound
+    /// forbids models of the same or higher cost, until the problem becomes
+    /// unsatisfiable.
+    /// The bound is encoded as a generalized totalizer and removed again afterwards.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::{AtMostK, Or}, Lit};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// encoder.add_constraint(AtMostK { k: 1, lits: ["a", "b", "c"].iter().copied() });
+    ///
+    /// encoder.add_soft(Lit::Pos("a"), 3);
+    /// encoder.add_soft(Lit::Pos("b"), 2);
+    /// encoder.add_soft(Or(vec![Lit::Pos("b"), Lit::Pos("c")].into_iter()), 2);
+    ///
+    /// let (model, cost) = encoder.solve_optimal().unwrap();
+    /// assert_eq!(cost, 3);
+    /// assert!(model["b"]);
+    /// # }
+    /// ```
+    pub fn solve_optimal(&mut self) -> Option<(Model<V>, u64)> {
+        let terms = self.soft_terms();
+        let context = self.varmap.new_var();
+
+        let result = self.minimize(&terms, context);
+
+        self.backend.add_clause(iter::once(-context));
+        result
+    }
+
+    /// Minimizes the weight of the true literals of `terms` with a linear SAT-UNSAT
+    /// search.
+    ///
+    /// All clauses are guarded by `context`, which is assumed in each solve call.
+    /// Once the optimum is found, it stays a bound in `context`.
+    fn minimize(
+        &mut self,
+        terms: &[(u64, i32)],
+        context: i32,
+    ) -> Option<(Model<V>, u64)> {
+        if self.solve_assuming(vec![context]) != SolveResult::Sat {
+            return None;
+        }
+        let mut model = self.model();
+        let mut cost = self.cost(terms);
+
+        // Guard of the bounds which are only valid during the search.
+        let search = self.varmap.new_var();
+        let mut outputs = Vec::new();
+        let mut forbidden = 0;
+        let mut result = Some(());
+
+        while cost > 0 {
+            if outputs.is_empty() {
+                // Sums above the first cost are never allowed.
+                let mut backend = GuardedBackend {
+                    backend: &mut self.backend,
+                    guards: &[context],
+                };
+                outputs = encode_generalized_totalizer(
+                    terms,
+                    cost + 1,
+                    Direction::InToOut,
+                    &mut backend,
+                    &mut self.varmap,
+                );
+                forbidden = outputs.len();
+            }
+
+            let mut backend = GuardedBackend {
+                backend: &mut self.backend,
+                guards: &[context, search],
+            };
+            while forbidden > 0 && outputs[forbidden - 1].0 >= cost {
+                forbidden -= 1;
+                backend.add_clause(iter::once(-outputs[forbidden].1));
+            }
+
+            match self.solve_assuming(vec![context, search]) {
+                SolveResult::Sat => {
+                    model = self.model();
+                    cost = self.cost(terms);
+                }
+                SolveResult::Unsat(_) => break,
+                SolveResult::Interrupted | SolveResult::Unknown => {
+                    result = None;
+                    break;
+                }
+            }
+        }
+
+        self.backend.add_clause(iter::once(-search));
+
+        let mut backend = GuardedBackend {
+            backend: &mut self.backend,
+            guards: &[context],
+        };
+        if outputs.is_empty() && cost == 0 {
+            // The first model is optimal, so no sum was encoded.
+            for &(_, lit) in terms.iter().filter(|(weight, _)| *weight > 0) {
+                backend.add_clause(iter::once(-lit));
+            }
+        }
+        for &(_, lit) in outputs.iter().filter(|(sum, _)| *sum > cost) {
+            backend.add_clause(iter::once(-lit));
+        }
+
+        result.map(|_| (model, cost))
+    }
+
+    /// Weighted literals which are true if a soft constraint is violated.
+    fn soft_terms(&self) -> Vec<(u64, i32)> {
+        self.soft.iter().map(|s| (s.weight, -s.lit)).collect()
+    }
+
+    /// Weight of the true literals of `terms` in the last model.
+    pub(crate) fn cost(&mut self, terms: &[(u64, i32)]) -> u64 {
+        let backend = &mut self.backend;
+        terms
+            .iter()
+            .filter(|(_, lit)| backend.value(*lit))
+            .map(|(weight, _)| weight)
+            .sum()
+    }
+}
+
+/// Linear objective which is minimized, see
+/// [`solve_lexicographic`](Encoder::solve_lexicographic).
+///
+/// The cost of a model is the sum of the weights of the true literals.
+#[derive(Debug, Clone)]
+pub struct Objective<V> {
+    terms: Vec<(u64, VarType<V>)>,
+}
+
+impl<V: SatVar> Objective<V> {
+    /// Creates an objective without any costs.
+    pub fn new() -> Self {
+        Self { terms: Vec::new() }
+    }
+
+    /// Pay `weight` if `lit` is true.
+    pub fn add_lit<L: Into<VarType<V>>>(&mut self, lit: L, weight: u64) {
+        self.terms.push((weight, lit.into()));
+    }
+
+    /// Pay `weight` if `constraint` is violated.
+    ///
+    /// The constraint is encoded right away, like the soft constraints of
+    /// [`add_soft`](Encoder::add_soft). Unlike those, it is only part of this
+    /// objective.
+    pub fn add_constraint<C, B>(
+        &mut self,
+        encoder: &mut Encoder<V, B>,
+        constraint: C,
+        weight: u64,
+    ) where
+        C: ConstraintRepr<V>,
+        B: Backend,
+    {
+        let violated = encoder.add_constraint_implies_repr(Not(constraint));
+        self.add_lit(violated, weight);
+    }
+
+    /// Weighted literals of the objective as integer SAT variables.
+    pub(crate) fn resolve(&self, varmap: &mut VarMap<V>) -> Vec<(u64, i32)> {
+        self.terms
+            .iter()
+            .map(|(weight, lit)| (*weight, varmap.add_var(lit.clone())))
+            .collect()
+    }
+}
+
+impl<V: SatVar> Default for Objective<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: SatVar, L: Into<VarType<V>>> iter::FromIterator<(L, u64)> for Objective<V> {
+    fn from_iter<I: IntoIterator<Item = (L, u64)>>(iter: I) -> Self {
+        let mut objective = Self::new();
+        for (lit, weight) in iter {
+            objective.add_lit(lit, weight);
+        }
+        objective
+    }
+}
+
+impl<V: SatVar, S: IncrementalSolver> Encoder<V, S> {
+    /// Minimizes the objectives one after another.
+    ///
+    /// Each objective is minimized with the linear search of
+    /// [`solve_optimal`](Encoder::solve_optimal). Its optimum is then fixed as a hard
+    /// bound while the following objectives are minimized, so a worse value of a
+    /// later objective is never traded for a better value of an earlier one.
+    /// The bounds are removed again once all objectives are solved.
+    /// Soft constraints added with [`add_soft`](Encoder::add_soft) are ignored.
+    ///
+    /// Returns a model which is optimal for all objectives and the optimal cost of
+    /// each objective.
+    /// Returns `None` if the problem is unsatisfiable or the solver failed.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::{ExactlyK, Or}, Lit, Objective};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// // Plan 2 of 4 shifts.
+    /// encoder.add_constraint(ExactlyK { k: 2, lits: 0..4 });
+    ///
+    /// let mut preferences = Objective::new();
+    /// preferences.add_constraint(
+    ///     &mut encoder,
+    ///     Or(vec![Lit::Pos(0), Lit::Pos(3)].into_iter()),
+    ///     1,
+    /// );
+    /// let cost: Objective<_> = vec![(0, 4), (1, 2), (2, 1), (3, 3)].into_iter().collect();
+    /// let changes: Objective<_> = vec![(Lit::Neg(0), 1), (Lit::Neg(3), 1)].into_iter().collect();
+    ///
+    /// let (model, costs) = encoder
+    ///     .solve_lexicographic(&[preferences, cost, changes])
+    ///     .unwrap();
+    /// assert_eq!(costs, vec![0, 4, 1]);
+    /// assert!(model[2] && model[3]);
+    /// # }
+    /// ```
+    pub fn solve_lexicographic(
+        &mut self,
+        objectives: &[Objective<V>],
+    ) -> Option<(Model<V>, Vec<u64>)> {
+        let context = self.varmap.new_var();
+        let mut costs = Vec::with_capacity(objectives.len());
+        let mut model = None;
+
+        for objective in objectives {
+            let terms = objective.resolve(&mut self.varmap);
+
+            match self.minimize(&terms, context) {
+                Some((optimal, cost)) => {
+                    model = Some(optimal);
+                    costs.push(cost);
+                }
+                None => {
+                    model = None;
+                    break;
+                }
+            }
+        }
+
+        if objectives.is_empty()
+            && self.solve_assuming(vec![context]) == SolveResult::Sat
+        {
+            model = Some(self.model());
+        }
+
+        self.backend.add_clause(iter::once(-context));
+        model.map(|model| (model, costs))
+    }
+}
+
+/// Bounds on the optimal cost reported by
+/// [`solve_core_guided`](Encoder::solve_core_guided).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    /// The optimal cost is at least `lower`.
+    pub lower: u64,
+    /// Cost of the best model found so far.
+    pub upper: Option<u64>,
+}
+
+/// Assumption of the core-guided search which may be violated for `weight`.
+struct Term {
+    lit: i32,
+    weight: u64,
+    /// Index of the totalizer and the output index if `lit` is the negation of a
+    /// totalizer output.
+    output: Option<(usize, usize)>,
+}
+
+impl<V: SatVar, S: IncrementalSolver> Encoder<V, S> {
+    /// Solve the problem such that the weight of the violated soft constraints is
+    /// minimal, using the core-guided OLL algorithm.
+    ///
+    /// All soft constraints are assumed to be satisfied. Each unsatisfiable core
+    /// raises the lower bound by its smallest weight and is relaxed by a totalizer
+    /// over the constraints of the core, which allows one more of them to be
+    /// violated. Totalizers are extended incrementally if their outputs end up in
+    /// a core again.
+    /// With `stratify`, only soft constraints with large weights are assumed at
+    /// first, the others follow once the problem is satisfiable.
+    ///
+    /// `progress` is called whenever one of the bounds changes.
+    /// The result is the same as of [`solve_optimal`](Encoder::solve_optimal), but
+    /// this is usually much faster if there are many soft constraints.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::AtMostK, Lit};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// encoder.add_constraint(AtMostK { k: 2, lits: 0..6 });
+    /// for i in 0..6 {
+    ///     encoder.add_soft(Lit::Pos(i), i + 1);
+    /// }
+    ///
+    /// let mut bounds = Vec::new();
+    /// let (model, cost) = encoder
+    ///     .solve_core_guided(true, |b| bounds.push(b))
+    ///     .unwrap();
+    ///
+    /// assert_eq!(cost, 1 + 2 + 3 + 4);
+    /// assert!(model[4] && model[5]);
+    /// assert!(bounds.windows(2).all(|w| w[0].lower <= w[1].lower));
+    /// # }
+    /// ```
+    pub fn solve_core_guided<F>(
+        &mut self,
+        stratify: bool,
+        mut progress: F,
+    ) -> Option<(Model<V>, u64)>
+    where
+        F: FnMut(Bounds),
+    {
+        let guard = self.varmap.new_var();
+        let soft = self.soft_terms();
+        let mut terms: Vec<_> = self
+            .soft
+            .iter()
+            .filter(|s| s.weight > 0)
+            .map(|s| Term {
+                lit: s.lit,
+                weight: s.weight,
+                output: None,
+            })
+            .collect();
+        let mut totalizers = Vec::<Totalizer>::new();
+
+        let mut bounds = Bounds {
+            lower: 0,
+            upper: None,
+        };
+        let mut best = None;
+        let mut threshold = match stratify {
+            true => terms.iter().map(|t| t.weight).max().unwrap_or(1),
+            false => 1,
+        };
+
+        let result = loop {
+            let assumptions: Vec<_> = iter::once(guard)
+                .chain(
+                    terms
+                        .iter()
+                        .filter(|t| t.weight >= threshold)
+                        .map(|t| t.lit),
+                )
+                .collect();
+
+            match self.solve_assuming(assumptions) {
+                SolveResult::Sat => {
+                    let cost = self.cost(&soft);
+                    if bounds.upper.map_or(true, |upper| cost < upper) {
+                        best = Some(self.model());
+                        bounds.upper = Some(cost);
+                        progress(bounds);
+                    }
+
+                    let below = terms
+                        .iter()
+                        .map(|t| t.weight)
+                        .filter(|&w| w > 0 && w < threshold)
+                        .max();
+                    match below {
+                        Some(w) if bounds.upper != Some(bounds.lower) => {
+                            threshold = w
+                        }
+                        _ => break best.take(),
+                    }
+                }
+                SolveResult::Unsat(core) => {
+                    let core = core.unwrap_or_default();
+                    let in_core: Vec<_> = terms
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, t)| {
+                            t.weight >= threshold && core.contains(&t.lit)
+                        })
+                        .map(|(i, _)| i)
+                        .collect();
+                    // Without soft constraints in the core the hard constraints are
+                    // unsatisfiable.
+                    if in_core.is_empty() {
+                        break None;
+                    }
+
+                    let weight =
+                        in_core.iter().map(|&i| terms[i].weight).min().unwrap();
+
+                    bounds.lower += weight;
+                    progress(bounds);
+
+                    for &i in &in_core {
+                        terms[i].weight -= weight;
+                    }
+                    self.relax_core(
+                        guard,
+                        &in_core,
+                        weight,
+                        &mut terms,
+                        &mut totalizers,
+                    );
+                    terms.retain(|t| t.weight > 0);
+                }
+                SolveResult::Interrupted | SolveResult::Unknown => break None,
+            }
+        };
+
+        self.backend.add_clause(iter::once(-guard));
+        result.map(|model| (model, bounds.upper.unwrap()))
+    }
+
+    /// Allows one more violation among the terms of the core.
+    /// Terms which are totalizer outputs allow one more true input of their
+    /// totalizer instead.
+    fn relax_core(
+        &mut self,
+        guard: i32,
+        core: &[usize],
+        weight: u64,
+        terms: &mut Vec<Term>,
+        totalizers: &mut Vec<Totalizer>,
+    ) {
+        let mut backend = GuardedBackend {
+            backend: &mut self.backend,
+            guards: &[guard],
+        };
+
+        for &i in core {
+            if let Some((t, output)) = terms[i].output {
+                let totalizer = &mut totalizers[t];
+                if output + 1 < totalizer.len() {
+                    totalizer.extend(output + 2, &mut backend, &mut self.varmap);
+                    terms.push(Term {
+                        lit: -totalizer.outputs()[output + 1],
+                        weight,
+                        output: Some((t, output + 1)),
+                    });
+                }
+            }
+        }
+
+        if core.len() > 1 {
+            let violated: Vec<_> = core.iter().map(|&i| -terms[i].lit).collect();
+
+            let mut totalizer = Totalizer::new(&violated);
+            totalizer.extend(2, &mut backend, &mut self.varmap);
+
+            terms.push(Term {
+                lit: -totalizer.outputs()[1],
+                weight,
+                output: Some((totalizers.len(), 1)),
+            });
+            totalizers.push(totalizer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backend::CdclEncoder,
+        constraints::{AtLeastK, AtMostK, ExactlyK},
+        Lit, Objective,
+    };
+
+    #[test]
+    fn optimal_assignment() {
+        let mut encoder = CdclEncoder::<(u32, u32)>::new();
+
+        // Assign 4 jobs to 3 machines, each machine runs at most two jobs.
+        for job in 0..4 {
+            encoder.add_constraint(ExactlyK {
+                k: 1,
+                lits: (0..3).map(move |m| (job, m)),
+            });
+        }
+        for m in 0..3 {
+            encoder.add_constraint(AtMostK {
+                k: 2,
+                lits: (0..4).map(move |job| (job, m)),
+            });
+        }
+
+        // Every job prefers machine 0, but only two jobs fit.
+        for job in 0..4 {
+            encoder.add_soft(Lit::Pos((job, 0)), job as u64 + 1);
+        }
+        encoder.add_soft(
+            AtLeastK {
+                k: 2,
+                lits: (0..4).map(|j| (j, 1)),
+            },
+            4,
+        );
+
+        let (model, cost) = encoder.solve_optimal().unwrap();
+        assert_eq!(cost, 3);
+        assert!(model[(2, 0)] && model[(3, 0)]);
+        assert!(model[(0, 1)] && model[(1, 1)]);
+
+        // The bound doesn't survive the call.
+        encoder.add_constraint(Lit::Pos((0, 0)));
+        let (_, cost) = encoder.solve_optimal().unwrap();
+        assert_eq!(cost, 5);
+
+        encoder.push();
+        encoder.add_soft(Lit::Neg((0, 0)), 10);
+        assert_eq!(encoder.solve_optimal().unwrap().1, 15);
+        encoder.pop();
+        assert_eq!(encoder.solve_optimal().unwrap().1, 5);
+    }
+
+    #[test]
+    fn lexicographic_objectives() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(AtLeastK { k: 3, lits: 0..6 });
+        encoder.add_constraint(AtMostK { k: 1, lits: 0..3 });
+
+        let first: Objective<_> = (3..6).map(|i| (i, 1)).collect();
+        let second: Objective<_> =
+            vec![(0, 5), (1, 2), (2, 3), (3, 1)].into_iter().collect();
+        let mut third = Objective::new();
+        third.add_constraint(&mut encoder, Lit::Neg(4), 1);
+
+        let objectives = [first, second, third];
+        let (model, costs) = encoder.solve_lexicographic(&objectives).unwrap();
+        // Two of 3..6 are needed, the third objective comes too late to avoid 4.
+        assert_eq!(costs, vec![2, 2, 1]);
+        assert!(model[1] && model[4] && model[5] && !model[3]);
+
+        // Nothing stays frozen.
+        let (_, costs) = encoder.solve_lexicographic(&objectives[1..]).unwrap();
+        assert_eq!(costs, vec![1, 1]);
+        assert!(encoder.solve_lexicographic(&[]).is_some());
+
+        encoder.add_constraint(AtMostK { k: 1, lits: 3..6 });
+        assert!(encoder.solve_lexicographic(&objectives).is_none());
+    }
+
+    #[test]
+    fn zero_cost_objective_stays_optimal() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(AtLeastK { k: 1, lits: 0..3 });
+
+        // 3 doesn't occur in any clause, so the first model already has cost 0.
+        let first: Objective<_> = vec![(3, 1)].into_iter().collect();
+        let mut second = Objective::new();
+        second.add_lit(Lit::Neg(3), 1);
+
+        let (model, costs) = encoder.solve_lexicographic(&[first, second]).unwrap();
+        assert_eq!(costs, vec![0, 1]);
+        assert!(!model[3]);
+    }
+
+    #[test]
+    fn core_guided_matches_linear_search() {
+        let mut encoder = CdclEncoder::<u32>::new();
+
+        // Pick a subset of 12 items where neighbours exclude each other and
+        // at most 5 items are picked.
+        for i in 0..11 {
+            encoder.add_constraint(AtMostK {
+                k: 1,
+                lits: i..i + 2,
+            });
+        }
+        encoder.add_constraint(AtMostK { k: 5, lits: 0..12 });
+        for i in 0..12 {
+            encoder.add_soft(
+                Lit::Pos(i),
+                [5, 1, 3, 8, 2, 2, 7, 4, 1, 6, 3, 9][i as usize],
+            );
+        }
+        encoder.add_soft(AtLeastK { k: 2, lits: 6..9 }, 4);
+
+        let (_, expected) = encoder.solve_optimal().unwrap();
+
+        for &stratify in &[false, true] {
+            let mut bounds = Vec::new();
+            let (model, cost) = encoder
+                .solve_core_guided(stratify, |b| bounds.push(b))
+                .unwrap();
+            assert_eq!(cost, expected);
+
+            let picked: u64 = (0..12).filter(|&i| model[i]).count() as u64;
+            assert!(picked <= 5);
+
+            let last = bounds.last().unwrap();
+            assert_eq!((last.lower, last.upper), (cost, Some(cost)));
+            assert!(bounds.windows(2).all(|w| w[0].lower <= w[1].lower));
+        }
+
+        encoder.add_constraint(Lit::Neg(11));
+        let (_, expected) = encoder.solve_optimal().unwrap();
+        let (_, cost) = encoder.solve_core_guided(true, |_| {}).unwrap();
+        assert_eq!(cost, expected);
+
+        encoder.add_constraint(Lit::Pos(0));
+        encoder.add_constraint(Lit::Pos(1));
+        assert!(encoder.solve_core_guided(false, |_| {}).is_none());
+    }
+}
@@ -0,0 +1,308 @@
+use std::iter;
+
+use ahash::{HashMap, HashMapExt};
+
+use crate::{
+    circuit::Direction,
+    clause,
+    constraints::{
+        encode_cardinality_constraint, totalizer::encode_generalized_totalizer,
+    },
+    guard::GuardedBackend,
+    Backend, Encoder, IncrementalSolver, Model, Objective, SatVar, SolveResult,
+    VarType,
+};
+
+/// Iterator over the pareto optimal models of multiple objectives, created by
+/// [`Encoder::pareto_front`].
+///
+/// Each item is a model together with the costs of all objectives. No model is
+/// dominated by another one, i.e. each model is better than every other model in
+/// at least one objective. For each point of the pareto front only one model is
+/// returned.
+///
+/// A model is first improved until no model dominates it. Afterwards all models
+/// it dominates, including the ones with the same costs, are blocked.
+/// The blocking constraints are disabled once the iterator is dropped.
+pub struct ParetoFront<'a, V, S: IncrementalSolver> {
+    encoder: &'a mut Encoder<V, S>,
+    objectives: Vec<Vec<(u64, i32)>>,
+    /// Outputs of the counter of each objective together with the sum they stand
+    /// for. An output is true if the cost is at least its sum.
+    counters: Vec<Vec<(u64, i32)>>,
+    /// Literals returned by `at_most` for each objective, by bound.
+    bounds: Vec<HashMap<u64, i32>>,
+    context: i32,
+    result: Option<SolveResult>,
+}
+
+impl<V: SatVar, S: IncrementalSolver> ParetoFront<'_, V, S> {
+    /// Returns true if the whole pareto front was enumerated.
+    /// This is false if the solver failed.
+    pub fn is_complete(&self) -> bool {
+        matches!(self.result, Some(SolveResult::Unsat(_)))
+    }
+
+    /// Cost of each objective in the last model.
+    fn costs(&mut self) -> Vec<u64> {
+        let encoder = &mut self.encoder;
+        self.objectives
+            .iter()
+            .map(|terms| encoder.cost(terms))
+            .collect()
+    }
+
+    /// Returns a literal which implies that the cost of `objective` is at most
+    /// `bound`. The literal is only encoded once for each bound.
+    fn at_most(&mut self, objective: usize, bound: u64) -> i32 {
+        if let Some(&lit) = self.bounds[objective].get(&bound) {
+            return lit;
+        }
+
+        let lit = self.encoder.varmap.new_var();
+        let mut backend = GuardedBackend {
+            backend: &mut self.encoder.backend,
+            guards: &[self.context],
+        };
+
+        for &(_, output) in self.counters[objective]
+            .iter()
+            .filter(|(sum, _)| *sum > bound)
+        {
+            backend.add_clause(clause![-lit, -output]);
+        }
+
+        self.bounds[objective].insert(bound, lit);
+        lit
+    }
+
+    /// Encodes under `guards` that some objective is below `costs`.
+    fn encode_improvement(&mut self, costs: &[u64], guards: &[i32]) {
+        let better: Vec<_> = (0..costs.len())
+            .filter(|&i| costs[i] > 0)
+            .map(|i| self.at_most(i, costs[i] - 1))
+            .collect();
+
+        let mut backend = GuardedBackend {
+            backend: &mut self.encoder.backend,
+            guards,
+        };
+        backend.add_clause(better.into_iter());
+    }
+
+    /// Improves the last model until no other model dominates it.
+    fn improve(&mut self) -> Result<(Model<V>, Vec<u64>), SolveResult> {
+        let mut model = self.encoder.model();
+        let mut costs = self.costs();
+
+        loop {
+            let step = self.encoder.varmap.new_var();
+            let guards = [self.context, step];
+
+            for (i, &cost) in costs.iter().enumerate() {
+                let lit = self.at_most(i, cost);
+                GuardedBackend {
+                    backend: &mut self.encoder.backend,
+                    guards: &guards,
+                }
+                .add_clause(iter::once(lit));
+            }
+            self.encode_improvement(&costs, &guards);
+
+            let result = self.encoder.solve_assuming(guards.to_vec());
+            self.encoder.backend.add_clause(iter::once(-step));
+
+            match result {
+                SolveResult::Sat => {
+                    model = self.encoder.model();
+                    costs = self.costs();
+                }
+                SolveResult::Unsat(_) => return Ok((model, costs)),
+                result => return Err(result),
+            }
+        }
+    }
+
+    fn find(&mut self) -> Result<(Model<V>, Vec<u64>), SolveResult> {
+        match self.encoder.solve_assuming(vec![self.context]) {
+            SolveResult::Sat => {}
+            result => return Err(result),
+        }
+
+        let (model, costs) = self.improve()?;
+        let context = [self.context];
+        self.encode_improvement(&costs, &context);
+
+        Ok((model, costs))
+    }
+}
+
+impl<V: SatVar, S: IncrementalSolver> Iterator for ParetoFront<'_, V, S> {
+    type Item = (Model<V>, Vec<u64>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.result.is_some() {
+            return None;
+        }
+
+        match self.find() {
+            Ok(item) => Some(item),
+            Err(result) => {
+                self.result = Some(result);
+                None
+            }
+        }
+    }
+}
+
+impl<V, S: IncrementalSolver> Drop for ParetoFront<'_, V, S> {
+    fn drop(&mut self) {
+        self.encoder.backend.add_clause(iter::once(-self.context));
+    }
+}
+
+impl<V: SatVar, S: IncrementalSolver> Encoder<V, S> {
+    /// Returns an iterator over the pareto optimal models of `objectives`.
+    ///
+    /// Objectives with unit weights are counted with the sequential counter of the
+    /// cardinality constraints, weighted ones with a generalized totalizer.
+    /// See [`ParetoFront`] for details.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::AtLeastK, Lit, Objective};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// // Pick at least 2 of 4 items, each one has a price and a penalty.
+    /// encoder.add_constraint(AtLeastK { k: 2, lits: 0..4 });
+    ///
+    /// let price: Objective<_> = vec![(0, 1), (1, 2), (2, 3), (3, 4)].into_iter().collect();
+    /// let penalty: Objective<_> = vec![(0, 4), (1, 3), (2, 1), (3, 1)].into_iter().collect();
+    ///
+    /// let mut front: Vec<_> = encoder
+    ///     .pareto_front(&[price, penalty])
+    ///     .map(|(_, costs)| costs)
+    ///     .collect();
+    /// front.sort();
+    ///
+    /// assert_eq!(front, vec![vec![3, 7], vec![4, 5], vec![5, 4], vec![7, 2]]);
+    /// # }
+    /// ```
+    pub fn pareto_front(
+        &mut self,
+        objectives: &[Objective<V>],
+    ) -> ParetoFront<'_, V, S> {
+        let context = self.varmap.new_var();
+
+        let objectives: Vec<_> = objectives
+            .iter()
+            .map(|objective| {
+                let mut terms = objective.resolve(&mut self.varmap);
+                terms.retain(|(weight, _)| *weight > 0);
+                terms
+            })
+            .collect();
+
+        let counters = objectives
+            .iter()
+            .map(|terms| self.encode_counter(terms, context))
+            .collect();
+        let bounds = objectives.iter().map(|_| HashMap::new()).collect();
+
+        ParetoFront {
+            encoder: self,
+            objectives,
+            counters,
+            bounds,
+            context,
+            result: None,
+        }
+    }
+
+    /// Encodes a counter over the weighted literals `terms` under `guard`.
+    fn encode_counter(
+        &mut self,
+        terms: &[(u64, i32)],
+        guard: i32,
+    ) -> Vec<(u64, i32)> {
+        let mut backend = GuardedBackend {
+            backend: &mut self.backend,
+            guards: &[guard],
+        };
+
+        if terms.is_empty() {
+            Vec::new()
+        } else if terms.iter().all(|(weight, _)| *weight == 1) {
+            let outputs = encode_cardinality_constraint(
+                terms.iter().map(|&(_, lit)| VarType::Unnamed(lit)),
+                terms.len() as u32,
+                Direction::InToOut,
+                None,
+                &mut backend,
+                &mut self.varmap,
+            );
+            (1..).zip(outputs).collect()
+        } else {
+            let total = terms.iter().map(|(weight, _)| weight).sum();
+            encode_generalized_totalizer(
+                terms,
+                total,
+                Direction::InToOut,
+                &mut backend,
+                &mut self.varmap,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backend::CdclEncoder,
+        constraints::{AtMostK, ExactlyK},
+        Lit, Objective,
+    };
+
+    #[test]
+    fn pareto_front_of_counts() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(ExactlyK { k: 4, lits: 0..8 });
+        encoder.add_constraint(AtMostK { k: 1, lits: 2..4 });
+
+        // Count the true literals in 0..4 and in 4..8, 7 counts twice.
+        let first: Objective<_> = (0..4).map(|i| (Lit::Pos(i), 1)).collect();
+        let mut second: Objective<_> = (4..8).map(|i| (Lit::Pos(i), 1)).collect();
+        second.add_lit(Lit::Pos(7), 1);
+
+        let mut front = encoder.pareto_front(&[first, second]);
+        let mut points = Vec::new();
+        for (model, costs) in front.by_ref() {
+            let first = (0..4).filter(|&i| model[i]).count() as u64;
+            assert_eq!(costs[0], first);
+            points.push(costs);
+        }
+        assert!(front.is_complete());
+        drop(front);
+
+        points.sort();
+        assert_eq!(points, vec![vec![0, 5], vec![1, 3], vec![2, 2], vec![3, 1]]);
+
+        // The blocking constraints are disabled.
+        assert!(encoder.solve().is_some());
+    }
+
+    #[test]
+    fn bound_literals_are_reused() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(AtMostK { k: 2, lits: 0..4 });
+
+        let objective: Objective<_> = (0..4).map(|i| (Lit::Pos(i), 1)).collect();
+        let mut front = encoder.pareto_front(&[objective]);
+
+        let lit = front.at_most(0, 1);
+        assert_eq!(front.at_most(0, 1), lit);
+        assert_ne!(front.at_most(0, 0), lit);
+        assert_eq!(front.count(), 1);
+    }
+}
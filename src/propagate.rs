@@ -0,0 +1,446 @@
+use ahash::{HashSet, HashSetExt};
+
+use crate::{ClauseDatabase, Encoder, Lit, SatVar};
+
+/// Assumptions of [`Encoder::propagate`] which can't be true at the same time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict<V> {
+    /// Subset of the assumptions which leads to a conflict by unit propagation,
+    /// in the order in which they were given.
+    /// Empty if the clauses already conflict without any assumptions.
+    pub assumptions: Vec<Lit<V>>,
+}
+
+/// Why a variable was assigned.
+#[derive(Debug, Clone, Copy)]
+enum Reason {
+    Assumption,
+    Clause(usize),
+}
+
+fn index(lit: i32) -> usize {
+    2 * lit.unsigned_abs() as usize + (lit < 0) as usize
+}
+
+fn lit_value(assigned: &[Option<(bool, Reason)>], lit: i32) -> Option<bool> {
+    assigned[lit.unsigned_abs() as usize].map(|(value, _)| value == (lit > 0))
+}
+
+/// Unit propagation with two watched literals over a growing set of clauses.
+///
+/// Clauses are attached on the next call of [`propagate`](Propagator::propagate).
+/// The literals implied by the clauses alone are kept between calls, the
+/// assumptions and their implications are undone.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Propagator {
+    clauses: Vec<Vec<i32>>,
+    /// Number of clauses with watches.
+    attached: usize,
+    /// Clauses in which a literal is watched, indexed by [`index`].
+    watches: Vec<Vec<usize>>,
+    assigned: Vec<Option<(bool, Reason)>>,
+    trail: Vec<i32>,
+    head: usize,
+    /// Length of the trail implied without assumptions.
+    root: usize,
+    inconsistent: bool,
+}
+
+impl Propagator {
+    fn grow(&mut self, lit: i32) {
+        let var = lit.unsigned_abs() as usize;
+        if var >= self.assigned.len() {
+            self.assigned.resize(var + 1, None);
+            self.watches.resize(2 * var + 2, Vec::new());
+        }
+    }
+
+    pub fn add_clause(&mut self, mut clause: Vec<i32>) {
+        clause.sort_unstable_by_key(|l| (l.abs(), *l));
+        clause.dedup();
+        if clause.windows(2).any(|w| w[0] == -w[1]) {
+            return;
+        }
+
+        if let Some(&lit) = clause.last() {
+            self.grow(lit);
+        }
+        self.clauses.push(clause);
+    }
+
+    pub fn clauses(&self) -> &[Vec<i32>] {
+        &self.clauses
+    }
+
+    fn value(&self, lit: i32) -> Option<bool> {
+        lit_value(&self.assigned, lit)
+    }
+
+    fn assign(&mut self, lit: i32, reason: Reason) {
+        self.assigned[lit.unsigned_abs() as usize] = Some((lit > 0, reason));
+        self.trail.push(lit);
+    }
+
+    /// Watches the first two literals of the clause, preferring ones which aren't
+    /// false at the root.
+    fn attach(&mut self, i: usize) {
+        let assigned = &self.assigned;
+        let clause = &mut self.clauses[i];
+        clause.sort_by_key(|&l| match lit_value(assigned, l) {
+            Some(true) => 0,
+            None => 1,
+            Some(false) => 2,
+        });
+
+        let first = match clause.first() {
+            Some(&first) => first,
+            None => {
+                self.inconsistent = true;
+                return;
+            }
+        };
+        let second = clause.get(1).copied();
+
+        if let Some(second) = second {
+            self.watches[index(first)].push(i);
+            self.watches[index(second)].push(i);
+        }
+
+        let unit = match second {
+            Some(second) => self.value(second) == Some(false),
+            None => true,
+        };
+        match self.value(first) {
+            Some(false) => self.inconsistent = true,
+            None if unit => self.assign(first, Reason::Clause(i)),
+            _ => {}
+        }
+    }
+
+    /// Propagates all assignments on the trail.
+    /// Returns the index of a falsified clause on a conflict.
+    fn propagate_trail(&mut self) -> Result<(), usize> {
+        while self.head < self.trail.len() {
+            let false_lit = -self.trail[self.head];
+            self.head += 1;
+
+            let mut watchers = std::mem::take(&mut self.watches[index(false_lit)]);
+            let mut result = Ok(());
+            let mut w = 0;
+
+            while w < watchers.len() {
+                let i = watchers[w];
+                let assigned = &self.assigned;
+                let clause = &mut self.clauses[i];
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+
+                let first = clause[0];
+                if lit_value(assigned, first) == Some(true) {
+                    w += 1;
+                    continue;
+                }
+
+                let replacement = (2..clause.len())
+                    .find(|&k| lit_value(assigned, clause[k]) != Some(false));
+                if let Some(k) = replacement {
+                    clause.swap(1, k);
+                    self.watches[index(clause[1])].push(i);
+                    watchers.swap_remove(w);
+                    continue;
+                }
+
+                w += 1;
+                if self.value(first) == Some(false) {
+                    result = Err(i);
+                    break;
+                }
+                self.assign(first, Reason::Clause(i));
+            }
+
+            self.watches[index(false_lit)] = watchers;
+            if result.is_err() {
+                self.head = self.trail.len();
+                return result;
+            }
+        }
+        Ok(())
+    }
+
+    /// Collects the assumptions the falsified literals `lits` depend on.
+    fn analyze(&self, lits: &[i32]) -> Vec<i32> {
+        let mut stack: Vec<_> = lits.iter().map(|l| l.unsigned_abs()).collect();
+        let mut seen = HashSet::new();
+        let mut assumptions = Vec::new();
+
+        while let Some(var) = stack.pop() {
+            if !seen.insert(var) {
+                continue;
+            }
+
+            match self.assigned[var as usize].unwrap() {
+                (value, Reason::Assumption) => {
+                    let var = var as i32;
+                    assumptions.push(if value { var } else { -var })
+                }
+                (_, Reason::Clause(i)) => {
+                    stack.extend(self.clauses[i].iter().map(|l| l.unsigned_abs()))
+                }
+            }
+        }
+
+        assumptions
+    }
+
+    fn assume(&mut self, assumptions: &[i32]) -> Result<Vec<i32>, Vec<i32>> {
+        for &lit in assumptions {
+            self.grow(lit);
+
+            match self.value(lit) {
+                Some(true) => {}
+                Some(false) => {
+                    let mut failed = self.analyze(&[lit]);
+                    failed.push(lit);
+                    return Err(failed);
+                }
+                None => {
+                    self.assign(lit, Reason::Assumption);
+                    if let Err(i) = self.propagate_trail() {
+                        return Err(self.analyze(&self.clauses[i]));
+                    }
+                }
+            }
+        }
+        Ok(self.trail.clone())
+    }
+
+    /// See [`ClauseDatabase::propagate_assumptions`].
+    pub fn propagate(&mut self, assumptions: &[i32]) -> Result<Vec<i32>, Vec<i32>> {
+        while self.attached < self.clauses.len() && !self.inconsistent {
+            self.attach(self.attached);
+            self.attached += 1;
+        }
+        if !self.inconsistent && self.propagate_trail().is_err() {
+            self.inconsistent = true;
+        }
+        if self.inconsistent {
+            return Err(Vec::new());
+        }
+        self.root = self.trail.len();
+
+        let result = self.assume(assumptions);
+
+        for &lit in &self.trail[self.root..] {
+            self.assigned[lit.unsigned_abs() as usize] = None;
+        }
+        self.trail.truncate(self.root);
+        self.head = self.root;
+
+        result
+    }
+}
+
+impl<V: SatVar, S: ClauseDatabase> Encoder<V, S> {
+    /// Applies unit propagation to the clauses of the backend under `assumptions`
+    /// without solving the problem.
+    ///
+    /// Returns the named literals which are implied, in the order in which they
+    /// were derived. The assumptions themselves aren't part of the result.
+    /// If propagation runs into a conflict, the assumptions involved are returned.
+    /// Unit propagation is incomplete, so `Ok` doesn't mean that the problem is
+    /// satisfiable.
+    ///
+    /// Wrap solvers which don't implement [`ClauseDatabase`] in a
+    /// [`Recorder`](crate::backend::Recorder).
+    ///
+    /// # Example
+    /// ```rust
+    /// # use satoxid::{backend::CdclEncoder, constraints::{AtMostK, If}, Conflict, Lit};
+    /// # fn main() {
+    /// let mut encoder = CdclEncoder::new();
+    ///
+    /// encoder.add_constraint(AtMostK { k: 1, lits: ["a", "b", "c"].iter().copied() });
+    /// encoder.add_constraint(If { cond: Lit::Neg("b"), then: Lit::Pos("d") });
+    ///
+    /// let implied = encoder.propagate(&[Lit::Pos("a")]).unwrap();
+    /// assert_eq!(implied.len(), 3);
+    /// assert!(implied.contains(&Lit::Pos("d")));
+    ///
+    /// let conflict = encoder.propagate(&[Lit::Neg("d"), Lit::Pos("c"), Lit::Pos("e")]);
+    /// assert_eq!(
+    ///     conflict,
+    ///     Err(Conflict { assumptions: vec![Lit::Neg("d"), Lit::Pos("c")] })
+    /// );
+    /// # }
+    /// ```
+    pub fn propagate(
+        &mut self,
+        assumptions: &[Lit<V>],
+    ) -> Result<Vec<Lit<V>>, Conflict<V>> {
+        let assumptions: Vec<_> = assumptions
+            .iter()
+            .map(|lit| self.varmap.add_var(lit.clone()))
+            .collect();
+
+        let all: Vec<_> = self
+            .activation
+            .enabled
+            .iter()
+            .chain(&assumptions)
+            .copied()
+            .collect();
+
+        let trail = match self.backend.propagate_assumptions(&all) {
+            Ok(trail) => trail,
+            Err(failed) => {
+                // Activation literals are left out of the conflict.
+                let mut failed: HashSet<i32> = failed.into_iter().collect();
+                let failed = assumptions
+                    .iter()
+                    .filter(|lit| failed.remove(lit))
+                    .map(|&lit| self.varmap.lookup(lit).unwrap())
+                    .collect();
+                return Err(Conflict {
+                    assumptions: failed,
+                });
+            }
+        };
+
+        let assumed: HashSet<i32> = assumptions.iter().map(|l| l.abs()).collect();
+        let implied = trail
+            .into_iter()
+            .filter(|l| !assumed.contains(&l.abs()))
+            .filter(|&l| !self.varmap.is_stale_id(l))
+            .filter_map(|l| self.varmap.lookup(l))
+            .collect();
+        Ok(implied)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::{Cdcl, CdclEncoder, DimacsWriter, Recorder},
+        constraints::{AtMostK, ExactlyK, If},
+    };
+
+    #[test]
+    fn propagator_is_incremental() {
+        let mut propagator = Propagator::default();
+        propagator.add_clause(vec![1, 2, 3]);
+        propagator.add_clause(vec![-1, 4]);
+        assert_eq!(propagator.propagate(&[-2, -3]), Ok(vec![-2, -3, 1, 4]));
+
+        // Implications of new clauses are kept, the assumptions are undone.
+        propagator.add_clause(vec![-4]);
+        assert_eq!(propagator.propagate(&[-2]), Ok(vec![-4, -1, -2, 3]));
+        assert_eq!(propagator.propagate(&[-2, -3]), Err(vec![-2, -3]));
+
+        propagator.add_clause(vec![2, 4]);
+        assert_eq!(propagator.propagate(&[]), Ok(vec![-4, -1, 2]));
+
+        propagator.add_clause(vec![-2]);
+        assert_eq!(propagator.propagate(&[]), Err(vec![]));
+    }
+
+    fn implied<S: ClauseDatabase>(
+        encoder: &mut Encoder<u32, S>,
+        assumptions: &[Lit<u32>],
+    ) -> Result<Vec<Lit<u32>>, Conflict<u32>> {
+        encoder.propagate(assumptions).map(|mut implied| {
+            implied.sort();
+            implied
+        })
+    }
+
+    #[test]
+    fn backends_agree() {
+        let mut cdcl = CdclEncoder::<u32>::new();
+        let mut recorder = Encoder::<u32, Recorder<Cdcl>>::new();
+        let mut writer = Encoder::<u32, DimacsWriter>::new();
+
+        for (i, lits) in [0..4, 2..6, 4..8].iter().enumerate() {
+            let constraint = ExactlyK {
+                k: i as u32 + 1,
+                lits: lits.clone(),
+            };
+            cdcl.add_constraint(constraint.clone());
+            recorder.add_constraint(constraint.clone());
+            writer.add_constraint(constraint);
+        }
+        // Cdcl ignores the clauses it learns while solving.
+        assert!(cdcl.solve().is_some());
+
+        let cases = [
+            vec![Lit::Pos(0)],
+            vec![Lit::Pos(2), Lit::Neg(6)],
+            vec![Lit::Pos(0), Lit::Pos(1)],
+            vec![Lit::Neg(4), Lit::Neg(5), Lit::Neg(6)],
+        ];
+        for assumptions in &cases {
+            let expected = implied(&mut writer, assumptions);
+            assert_eq!(implied(&mut cdcl, assumptions), expected);
+            assert_eq!(implied(&mut recorder, assumptions), expected);
+        }
+    }
+
+    #[test]
+    fn propagate_implications() {
+        let mut encoder = CdclEncoder::<u32>::new();
+        encoder.add_constraint(ExactlyK { k: 1, lits: 0..3 });
+        encoder.add_constraint(If {
+            cond: Lit::Pos(2),
+            then: Lit::Pos(3),
+        });
+
+        let mut implied = encoder.propagate(&[Lit::Pos(2)]).unwrap();
+        implied.sort();
+        assert_eq!(implied, vec![Lit::Neg(0), Lit::Neg(1), Lit::Pos(3)]);
+
+        let implied = encoder.propagate(&[Lit::Neg(0), Lit::Neg(1)]).unwrap();
+        assert!(implied.contains(&Lit::Pos(2)) && implied.contains(&Lit::Pos(3)));
+
+        let conflict = encoder
+            .propagate(&[Lit::Pos(4), Lit::Neg(3), Lit::Neg(0), Lit::Neg(1)])
+            .unwrap_err();
+        assert_eq!(
+            conflict.assumptions,
+            vec![Lit::Neg(3), Lit::Neg(0), Lit::Neg(1)]
+        );
+
+        let conflict = encoder.propagate(&[Lit::Pos(1), Lit::Neg(1)]).unwrap_err();
+        assert_eq!(conflict.assumptions, vec![Lit::Pos(1), Lit::Neg(1)]);
+    }
+
+    #[test]
+    fn propagate_with_recorder() {
+        let mut encoder = Encoder::<u32, Recorder<Cdcl>>::new();
+        encoder.add_constraint(AtMostK { k: 1, lits: 0..4 });
+        assert_eq!(encoder.propagate(&[]), Ok(vec![]));
+
+        let guard = encoder.add_guarded_constraint(Lit::Pos(0));
+        let mut implied = encoder.propagate(&[]).unwrap();
+        implied.sort();
+        assert_eq!(
+            implied,
+            vec![Lit::Pos(0), Lit::Neg(1), Lit::Neg(2), Lit::Neg(3)]
+        );
+
+        encoder.disable_guard(&guard);
+        assert_eq!(encoder.propagate(&[]), Ok(vec![]));
+
+        encoder.add_constraint(Lit::Pos(1));
+        let conflict = encoder.propagate(&[Lit::Pos(2)]).unwrap_err();
+        assert_eq!(conflict.assumptions, vec![Lit::Pos(2)]);
+
+        encoder.add_constraint(Lit::Pos(3));
+        assert_eq!(
+            encoder.propagate(&[]),
+            Err(Conflict {
+                assumptions: vec![]
+            })
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use ahash::{HashMap, HashMapExt};
+use ahash::{HashMap, HashSet};
 use core::fmt::{self, Debug};
 
 use crate::{Lit, SatVar, VarType};
@@ -11,6 +11,7 @@ pub struct VarMap<V> {
     forward: HashMap<V, i32>,
     reverse: HashMap<i32, V>,
     next_id: i32,
+    stale: HashSet<i32>,
 }
 
 impl<V: SatVar> PartialEq for VarMap<V> {
@@ -27,6 +28,7 @@ impl<V> Default for VarMap<V> {
             forward: Default::default(),
             reverse: Default::default(),
             next_id: 1,
+            stale: Default::default(),
         }
     }
 }
@@ -59,8 +61,9 @@ impl<V: SatVar> VarMap<V> {
             Lit::Neg(v) => (v, -1),
         };
 
-        let id = if let Some(id) = self.forward.get(&var) {
-            *id
+        let id = if let Some(&id) = self.forward.get(&var) {
+            self.stale.remove(&id);
+            id
         } else {
             let id = self.new_var();
 
@@ -102,6 +105,38 @@ impl<V: SatVar> VarMap<V> {
         }
     }
 
+    /// Returns if `var` was introduced in a scope which has been popped.
+    /// Stale variables aren't part of models until they are used again.
+    ///
+    /// See [`Encoder::pop`](crate::Encoder::pop).
+    pub fn is_stale(&self, var: &V) -> bool {
+        self.forward
+            .get(var)
+            .is_some_and(|id| self.stale.contains(id))
+    }
+
+    pub(crate) fn is_stale_id(&self, id: i32) -> bool {
+        self.stale.contains(&id.abs())
+    }
+
+    /// Marks all variables with an id of at least `first` stale.
+    pub(crate) fn mark_stale_from(&mut self, first: i32) {
+        let stale = self.reverse.keys().copied().filter(|&id| id >= first);
+        self.stale.extend(stale);
+    }
+
+    /// Returns all named variables which aren't stale, ordered by their id.
+    pub(crate) fn named_vars(&self) -> Vec<(i32, &V)> {
+        let mut vars: Vec<_> = self
+            .reverse
+            .iter()
+            .filter(|(id, _)| !self.stale.contains(id))
+            .map(|(&id, var)| (id, var))
+            .collect();
+        vars.sort_unstable_by_key(|&(id, _)| id);
+        vars
+    }
+
     pub(crate) fn iter_internal_vars(&self) -> impl Iterator<Item = i32> {
         1..self.next_id
     }
@@ -114,6 +149,21 @@ impl<V> VarMap<V> {
         self.next_id += 1;
         id
     }
+
+    /// Number of fresh variables which can still be generated.
+    pub(crate) fn free_vars(&self) -> u32 {
+        (i32::MAX - self.next_id) as u32
+    }
+
+    /// Generates `count` consecutive fresh variables and returns the first one.
+    ///
+    /// Panics if there are less than `count` free variables left.
+    pub(crate) fn new_vars(&mut self, count: u32) -> i32 {
+        assert!(count <= self.free_vars(), "not enough free variables left");
+        let first = self.next_id;
+        self.next_id += count as i32;
+        first
+    }
 }
 
 #[cfg(feature = "serde")]
@@ -218,6 +268,7 @@ mod serde {
                         forward: forward_map,
                         reverse: reverse_map,
                         next_id,
+                        stale: Default::default(),
                     })
                 }
 
@@ -260,6 +311,7 @@ mod serde {
                         forward: forward_map,
                         reverse: reverse_map,
                         next_id,
+                        stale: Default::default(),
                     })
                 }
             }